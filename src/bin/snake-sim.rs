@@ -0,0 +1,219 @@
+//! Headless batch runner. Plays many games without opening a window, each one
+//! driven by one of the bots in `src/bot.rs`, and prints aggregate statistics so that rule and speed
+//! changes can be evaluated offline.
+//!
+//! ```
+//! $ cargo run --release --no-default-features --bin snake-sim -- --games 100000 --bot greedy
+//! ```
+//!
+//! With `--verify` it instead plays back replay files and checks that they still
+//! end the way they were recorded, which catches rule changes that alter history.
+
+use std::env;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use snake_remix::bot::{Bot, View};
+use snake_remix::difficulty::Difficulty;
+use snake_remix::level::Level;
+use snake_remix::replay::Replay;
+use snake_remix::sim::{Boundary, Cause, GameStates, Grid, Input, Rules, Simulation};
+
+const USAGE: &str = "usage: snake-sim [--games N] [--max-ticks N] [--bot greedy|random|bfs|hamiltonian] [--threads N] [--seed N] [--walls] [--power-ups] [--food POLICY] [--difficulty NAME] [--speed CURVE] [--mode MODE] [--board WxH] [--level NAME]\n       snake-sim --verify REPLAY...";
+
+struct Options {
+    games: u32,
+    max_ticks: u64,
+    bot: Bot,
+    threads: u32,
+    /// Game number `i` is played with seed `seed + i`, so a whole batch is reproducible.
+    seed: u64,
+    level: Level,
+    rules: Rules,
+    /// Replay files to verify instead of simulating.
+    verify: Vec<String>,
+}
+
+impl Options {
+    fn from_args() -> Result<Options, String> {
+        let mut options = Options {
+            games: 1000,
+            max_ticks: 10_000,
+            bot: Bot::Greedy,
+            threads: thread::available_parallelism().map_or(1, |n| n.get() as u32),
+            seed: Simulation::random_seed(),
+            level: Level::open(Grid::default()),
+            rules: Rules::default(),
+            verify: Vec::new(),
+        };
+
+        let mut solid = false;
+        let mut from_level = false;
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--games" => options.games = value()?.parse().map_err(|e| format!("--games: {}", e))?,
+                "--max-ticks" => {
+                    options.max_ticks = value()?.parse().map_err(|e| format!("--max-ticks: {}", e))?
+                }
+                "--bot" => options.bot = value()?.parse()?,
+                "--threads" => {
+                    options.threads = value()?.parse().map_err(|e| format!("--threads: {}", e))?
+                }
+                "--seed" => options.seed = value()?.parse().map_err(|e| format!("--seed: {}", e))?,
+                "--walls" => solid = true,
+                "--power-ups" => options.rules.power_ups = true,
+                "--difficulty" => {
+                    let difficulty: Difficulty = value()?.parse().map_err(|e| format!("--difficulty: {}", e))?;
+                    options.rules.speed = difficulty.speed();
+                }
+                "--speed" => options.rules.speed = value()?.parse().map_err(|e| format!("--speed: {}", e))?,
+                "--mode" => options.rules.mode = value()?.parse().map_err(|e| format!("--mode: {}", e))?,
+                "--food" => options.rules.food = value()?.parse().map_err(|e| format!("--food: {}", e))?,
+                "--board" => {
+                    let grid: Grid = value()?.parse().map_err(|e| format!("--board: {}", e))?;
+                    options.level = Level::open(grid);
+                    from_level = false;
+                }
+                "--level" => {
+                    let name = value()?;
+                    options.level = Level::find(&name).map_err(|e| format!("level {}: {}", name, e))?;
+                    from_level = true;
+                }
+                "--verify" => options.verify.extend(args.by_ref()),
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                other => return Err(format!("unknown argument: {}\n{}", other, USAGE)),
+            }
+        }
+        // A level sets its own boundary; `--walls` is only for open boards.
+        if solid && from_level {
+            return Err("--walls cannot be used with --level".to_string());
+        }
+        if solid {
+            options.level.grid.boundary = Boundary::Solid;
+        }
+
+        Ok(options)
+    }
+}
+
+/// Why a game ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Ending {
+    /// The snake died; of `Cause::Timeout` if the game was still running after
+    /// `--max-ticks` ticks.
+    Died(Cause),
+    /// The snake filled the whole board.
+    Won,
+}
+
+/// The outcome of a single game.
+struct GameResult {
+    points: i32,
+    length: usize,
+    ticks: u64,
+    /// Sum of all tick intervals, i.e. how long the game would have lasted on screen.
+    game_time: Duration,
+    ending: Ending,
+}
+
+fn play(options: &Options, seed: u64) -> GameResult {
+    let mut sim = Simulation::with_rules(options.level.clone(), seed, 1, options.rules);
+    sim.max_ticks = Some(options.max_ticks);
+    // The bot gets its own seed so that it does not disturb the food sequence.
+    let mut bot = options.bot.controller(seed ^ 0x5eed);
+
+    while sim.game_states == GameStates::GameOn {
+        let inputs: Vec<Input> = bot
+            .next_direction(&View::new(&sim, 0))
+            .map(|dir| Input::Turn { snake: 0, dir })
+            .into_iter()
+            .collect();
+        sim.step(&inputs);
+    }
+
+    GameResult {
+        points: sim.snake().points,
+        length: sim.snake().length(),
+        ticks: sim.tick,
+        game_time: sim.game_time,
+        ending: match sim.snake().died {
+            Some(cause) => Ending::Died(cause),
+            None => Ending::Won,
+        },
+    }
+}
+
+/// Plays back every replay and reports the ones whose outcome changed.
+fn verify(paths: &[String]) -> bool {
+    let mut all_ok = true;
+    for path in paths {
+        let result = Replay::load(path)
+            .map_err(|e| e.to_string())
+            .and_then(|replay| replay.verify());
+        match result {
+            Ok(outcome) => println!("ok       {} (tick {}, {} points)", path, outcome.tick, outcome.points),
+            Err(e) => {
+                println!("MISMATCH {}: {}", path, e);
+                all_ok = false;
+            }
+        }
+    }
+    all_ok
+}
+
+fn main() {
+    let options = Options::from_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+
+    if !options.verify.is_empty() {
+        process::exit(if verify(&options.verify) { 0 } else { 1 });
+    }
+
+    let started = Instant::now();
+    // Games are independent of each other, so we simply split them between threads.
+    let threads = options.threads.clamp(1, options.games.max(1));
+    let results: Vec<GameResult> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|t| {
+                let options = &options;
+                // Thread `t` plays games t, t + threads, t + 2 * threads, ...
+                scope.spawn(move || {
+                    (t..options.games)
+                        .step_by(threads as usize)
+                        .map(|i| play(options, options.seed.wrapping_add(i as u64)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("simulation thread panicked"))
+            .collect()
+    });
+    let elapsed = started.elapsed();
+
+    let n = results.len().max(1) as f64;
+    let mean = |f: &dyn Fn(&GameResult) -> f64| results.iter().map(f).sum::<f64>() / n;
+    let count = |ending: Ending| results.iter().filter(|r| r.ending == ending).count();
+
+    println!("games:          {}", results.len());
+    println!("bot:            {}", options.bot);
+    println!("seed:           {}", options.seed);
+    println!("mean score:     {:.2}", mean(&|r| r.points as f64));
+    println!("max score:      {}", results.iter().map(|r| r.points).max().unwrap_or(0));
+    println!("mean length:    {:.2}", mean(&|r| r.length as f64));
+    println!("mean ticks:     {:.1}", mean(&|r| r.ticks as f64));
+    println!("mean game time: {:.1}s", mean(&|r| r.game_time.as_secs_f64()));
+    println!("deaths:");
+    println!("  itself:       {}", count(Ending::Died(Cause::Itself)));
+    println!("  wall:         {}", count(Ending::Died(Cause::Wall)));
+    println!("  timeout:      {}", count(Ending::Died(Cause::Timeout)));
+    println!("  starved:      {}", count(Ending::Died(Cause::Starved)));
+    println!("won:            {}", count(Ending::Won));
+    println!("simulated in {:.2}s", elapsed.as_secs_f64());
+}
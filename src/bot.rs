@@ -0,0 +1,418 @@
+//! Computer players.
+//!
+//! A snake is steered either by a person or by a `Controller`. Before every
+//! tick a controller is shown the board through a `View` and picks the
+//! direction its snake should go next. The bots in here are used as opponents
+//! in versus matches and by `snake-sim` to benchmark rule changes:
+//!
+//! * `Greedy` heads straight for the nearest food, avoiding anything lethal
+//!   next to it.
+//! * `Random` picks any move that is not lethal right away.
+//! * `Bfs` follows the shortest path to the nearest food, and when there is none
+//!   moves to where it has the most room left.
+//! * `Hamiltonian` follows a cycle through every cell of the board, which can
+//!   never run into its own body. It needs a board without walls with an even
+//!   number of rows or columns; otherwise it plays like `Bfs`.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::{Boundary, Direction, GameRng, Grid, GridPosition, Simulation, Snake};
+
+const DIRECTIONS: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+/// Steers a snake.
+pub trait Controller: Send {
+    /// The direction the snake should move in on the next tick, or `None` to
+    /// keep going the way it is.
+    fn next_direction(&mut self, view: &View) -> Option<Direction>;
+}
+
+/// What a controller gets to see: the whole board, read-only, and which snake
+/// is its own.
+pub struct View<'a> {
+    pub sim: &'a Simulation,
+    pub snake: usize,
+}
+
+impl<'a> View<'a> {
+    pub fn new(sim: &'a Simulation, snake: usize) -> Self {
+        View { sim, snake }
+    }
+
+    /// The snake being steered.
+    pub fn me(&self) -> &Snake {
+        &self.sim.snakes[self.snake]
+    }
+
+    pub fn grid(&self) -> &Grid {
+        self.sim.grid()
+    }
+
+    /// Where the snake's head would be after moving in `dir`.
+    pub fn next(&self, pos: GridPosition, dir: Direction) -> GridPosition {
+        GridPosition::new_from_move(pos, dir, self.grid())
+    }
+
+    /// Whether moving onto `pos` would kill a snake: a wall, off a solid board
+    /// or any part of any snake.
+    pub fn is_blocked(&self, pos: GridPosition) -> bool {
+        self.sim.occupancy().is_blocked(pos)
+    }
+
+    /// The directions the snake can move in without dying right away.
+    pub fn safe_directions(&self) -> Vec<Direction> {
+        let me = self.me();
+        DIRECTIONS
+            .iter()
+            .cloned()
+            .filter(|dir| *dir != me.last_update_dir.inverse())
+            .filter(|dir| !self.is_blocked(self.next(me.head.pos, *dir)))
+            .collect()
+    }
+
+    /// Number of moves between two cells, ignoring anything in between and
+    /// taking the short way round on a board that wraps around.
+    pub fn distance(&self, a: GridPosition, b: GridPosition) -> i16 {
+        let grid = self.grid();
+        let axis = |a: i16, b: i16, size: i16| {
+            let d = (a - b).abs();
+            match grid.boundary {
+                Boundary::Wrap => d.min(size - d),
+                Boundary::Solid => d,
+            }
+        };
+        axis(a.x, b.x, grid.width) + axis(a.y, b.y, grid.height)
+    }
+
+    fn index(&self, pos: GridPosition) -> usize {
+        pos.y as usize * self.grid().width as usize + pos.x as usize
+    }
+
+    /// Searches the free cells breadth first from `from`, returning for every
+    /// reachable cell the first move on a shortest path to it and the length
+    /// of that path. The search stops once the nearest food is found, so cells
+    /// further away than that are left out.
+    fn search(&self, from: GridPosition) -> Vec<Option<(Direction, u32)>> {
+        let cells = self.grid().width as usize * self.grid().height as usize;
+        let mut first_move = vec![None; cells];
+        let mut queue = VecDeque::new();
+        let mut food_at = None;
+        let occupancy = self.sim.occupancy();
+        for dir in self.safe_directions() {
+            let pos = self.next(from, dir);
+            if first_move[self.index(pos)].is_none() {
+                first_move[self.index(pos)] = Some((dir, 1));
+                queue.push_back(pos);
+                if occupancy.has_food(pos) {
+                    food_at = Some(1);
+                }
+            }
+        }
+        while let Some(pos) = queue.pop_front() {
+            let (dir, steps) = match first_move[self.index(pos)] {
+                Some(found) => found,
+                None => continue,
+            };
+            // Every cell as close as the nearest food has been found by now.
+            if food_at.is_some_and(|food_at| steps >= food_at) {
+                break;
+            }
+            for next in DIRECTIONS.iter().map(|d| self.next(pos, *d)) {
+                if !self.is_blocked(next) && first_move[self.index(next)].is_none() {
+                    first_move[self.index(next)] = Some((dir, steps + 1));
+                    queue.push_back(next);
+                    if food_at.is_none() && occupancy.has_food(next) {
+                        food_at = Some(steps + 1);
+                    }
+                }
+            }
+        }
+        first_move
+    }
+
+    /// Number of free cells reachable from `from`, `from` included.
+    fn room(&self, from: GridPosition) -> usize {
+        let cells = self.grid().width as usize * self.grid().height as usize;
+        let mut seen = vec![false; cells];
+        seen[self.index(from)] = true;
+        let mut queue = VecDeque::from(vec![from]);
+        let mut count = 0;
+        while let Some(pos) = queue.pop_front() {
+            count += 1;
+            for next in DIRECTIONS.iter().map(|d| self.next(pos, *d)) {
+                if !self.is_blocked(next) && !seen[self.index(next)] {
+                    seen[self.index(next)] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        count
+    }
+}
+
+/// The bots that come with the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bot {
+    Greedy,
+    Random,
+    Bfs,
+    Hamiltonian,
+}
+
+impl Bot {
+    pub const ALL: [Bot; 4] = [Bot::Greedy, Bot::Random, Bot::Bfs, Bot::Hamiltonian];
+
+    /// A new controller of this kind. `seed` seeds the bots that use randomness,
+    /// so that a game played by bots can be repeated.
+    pub fn controller(self, seed: u64) -> Box<dyn Controller> {
+        match self {
+            Bot::Greedy => Box::new(Greedy),
+            Bot::Random => Box::new(Random::new(seed)),
+            Bot::Bfs => Box::new(Bfs),
+            Bot::Hamiltonian => Box::new(Hamiltonian::default()),
+        }
+    }
+}
+
+impl fmt::Display for Bot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Bot::Greedy => "greedy",
+            Bot::Random => "random",
+            Bot::Bfs => "bfs",
+            Bot::Hamiltonian => "hamiltonian",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Bot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Bot::ALL
+            .iter()
+            .find(|bot| bot.to_string() == s)
+            .cloned()
+            .ok_or_else(|| format!("unknown bot `{}`", s))
+    }
+}
+
+/// Who steers a snake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Driver {
+    Human,
+    Bot(Bot),
+}
+
+impl Driver {
+    /// The controller steering the snake, if it is not a person.
+    pub fn controller(self, seed: u64) -> Option<Box<dyn Controller>> {
+        match self {
+            Driver::Human => None,
+            Driver::Bot(bot) => Some(bot.controller(seed)),
+        }
+    }
+}
+
+/// Heads straight for the nearest food, avoiding anything lethal next to it.
+pub struct Greedy;
+
+impl Controller for Greedy {
+    fn next_direction(&mut self, view: &View) -> Option<Direction> {
+        let head = view.me().head.pos;
+        view.safe_directions().into_iter().min_by_key(|dir| {
+            let next = view.next(head, *dir);
+            view.sim.foods.iter().map(|food| view.distance(next, food.pos)).min()
+        })
+    }
+}
+
+/// Picks any move that is not lethal right away.
+pub struct Random {
+    rng: GameRng,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random {
+            rng: GameRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Controller for Random {
+    fn next_direction(&mut self, view: &View) -> Option<Direction> {
+        view.safe_directions().choose(&mut self.rng).cloned()
+    }
+}
+
+/// Follows the shortest path to the nearest food. When there is none, it moves
+/// to where it has the most room left, hoping for a path to open up.
+pub struct Bfs;
+
+impl Controller for Bfs {
+    fn next_direction(&mut self, view: &View) -> Option<Direction> {
+        let head = view.me().head.pos;
+        let paths = view.search(head);
+        let nearest = view
+            .sim
+            .foods
+            .iter()
+            .filter_map(|food| paths[view.index(food.pos)])
+            .min_by_key(|(_, steps)| *steps);
+        if let Some((dir, _)) = nearest {
+            return Some(dir);
+        }
+        view.safe_directions()
+            .into_iter()
+            .max_by_key(|dir| view.room(view.next(head, *dir)))
+    }
+}
+
+/// Follows a cycle through every cell of the board.
+#[derive(Default)]
+pub struct Hamiltonian {
+    /// The cycle as the direction to leave every cell in, for the board it was
+    /// made for. Empty if that board has none.
+    cycle: Vec<Direction>,
+    grid: Option<Grid>,
+}
+
+impl Hamiltonian {
+    /// Makes a cycle for a board without walls with an even number of rows:
+    /// right along the top row, then snaking left and right through the other
+    /// rows, leaving out the first column, which leads back up to the start.
+    /// With an odd number of rows the same is done with the columns.
+    fn make_cycle(width: i16, height: i16) -> Vec<Direction> {
+        if width < 2 || height < 2 || (width % 2 != 0 && height % 2 != 0) {
+            return Vec::new();
+        }
+        let transposed = height % 2 != 0;
+        let (w, h) = if transposed { (height, width) } else { (width, height) };
+        let mut cycle = vec![Direction::Up; width as usize * height as usize];
+        for y in 0..h {
+            for x in 0..w {
+                let dir = if x == 0 {
+                    if y == 0 {
+                        Direction::Right
+                    } else {
+                        Direction::Up
+                    }
+                } else if y % 2 == 0 {
+                    if x == w - 1 {
+                        Direction::Down
+                    } else {
+                        Direction::Right
+                    }
+                } else if x == 1 && y != h - 1 {
+                    Direction::Down
+                } else {
+                    Direction::Left
+                };
+                // Transposed, rows become columns and the directions turn with them.
+                let (cx, cy, dir) = if transposed {
+                    let dir = match dir {
+                        Direction::Up => Direction::Left,
+                        Direction::Down => Direction::Right,
+                        Direction::Left => Direction::Up,
+                        Direction::Right => Direction::Down,
+                    };
+                    (y, x, dir)
+                } else {
+                    (x, y, dir)
+                };
+                cycle[cy as usize * width as usize + cx as usize] = dir;
+            }
+        }
+        cycle
+    }
+}
+
+impl Controller for Hamiltonian {
+    fn next_direction(&mut self, view: &View) -> Option<Direction> {
+        let grid = *view.grid();
+        if self.grid != Some(grid) {
+            self.grid = Some(grid);
+            self.cycle = if view.sim.level.walls().next().is_none() {
+                Hamiltonian::make_cycle(grid.width, grid.height)
+            } else {
+                Vec::new()
+            };
+        }
+        let head = view.me().head.pos;
+        // Until the snake is on its way along the cycle, e.g. because the cycle
+        // would turn it back into its own body, it plays like `Bfs`.
+        match self.cycle.get(view.index(head)) {
+            Some(dir) if view.safe_directions().contains(dir) => Some(*dir),
+            _ => Bfs.next_direction(view),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+    use crate::sim::{Food, GameStates, Input};
+
+    #[test]
+    fn hamiltonian_cycle_visits_every_cell_and_closes() {
+        for &(width, height) in &[(4, 4), (6, 5), (5, 6), (20, 12), (7, 10)] {
+            let grid = Grid {
+                boundary: Boundary::Solid,
+                ..Grid::new(width, height)
+            };
+            let cycle = Hamiltonian::make_cycle(width, height);
+            let mut seen = vec![false; cycle.len()];
+            let mut pos = GridPosition::new(0, 0);
+            for _ in 0..cycle.len() {
+                let i = pos.y as usize * width as usize + pos.x as usize;
+                assert!(!seen[i], "{}x{} visits {} twice", width, height, pos);
+                seen[i] = true;
+                pos = GridPosition::new_from_move(pos, cycle[i], &grid);
+                assert!(grid.contains(pos), "{}x{} leaves the board", width, height);
+            }
+            assert_eq!(pos, GridPosition::new(0, 0));
+        }
+        assert!(Hamiltonian::make_cycle(5, 7).is_empty());
+    }
+
+    #[test]
+    fn bfs_reaches_the_food() {
+        let mut sim = Simulation::new(Level::open(Grid::new(10, 8)), 1);
+        sim.foods = vec![Food::new(GridPosition::new(7, 1))];
+        sim.refresh();
+        let head = sim.snake().head.pos;
+        let distance = View::new(&sim, 0).distance(head, GridPosition::new(7, 1));
+        for _ in 0..distance {
+            let dir = Bfs.next_direction(&View::new(&sim, 0));
+            let inputs: Vec<Input> = dir.map(|dir| Input::Turn { snake: 0, dir }).into_iter().collect();
+            sim.step(&inputs);
+        }
+        assert_eq!(sim.snake().eaten, 1);
+    }
+
+    #[test]
+    fn no_bot_turns_back_into_its_neck() {
+        for bot in Bot::ALL.iter() {
+            let mut sim = Simulation::new(Level::open(Grid::new(12, 8)), 3);
+            let mut controller = bot.controller(3);
+            while sim.game_states == GameStates::GameOn && sim.tick < 500 {
+                let dir = controller.next_direction(&View::new(&sim, 0));
+                if let Some(dir) = dir {
+                    assert_ne!(dir, sim.snake().last_update_dir.inverse(), "{} turned back", bot);
+                }
+                let inputs: Vec<Input> = dir.map(|dir| Input::Turn { snake: 0, dir }).into_iter().collect();
+                sim.step(&inputs);
+            }
+        }
+    }
+}
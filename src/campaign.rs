@@ -0,0 +1,126 @@
+//! The campaign: levels played one after the other, each with a goal to reach.
+//!
+//! The stages are the level files in `resources/campaign`, in the order of
+//! their file names, and every one of them needs a `goal` record (see
+//! `src/level.rs`). Only the first stage can be played at first; reaching the
+//! goal of a stage unlocks the next one. How far the player got is stored as
+//! TOML in the user's data directory, e.g.
+//! `~/.local/share/snake-remix/campaign.toml` on Linux.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::level::{Level, LevelError};
+use crate::store::{self, StoreError};
+
+/// The stages of the campaign, in the order they are played in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Campaign {
+    pub stages: Vec<Level>,
+}
+
+impl Campaign {
+    /// The directory the stages are loaded from.
+    pub fn dir() -> PathBuf {
+        crate::resource_dir().join("campaign")
+    }
+
+    /// Loads every stage in `dir`, sorted by file name.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Campaign, LevelError> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .collect();
+        paths.sort();
+        let mut stages = Vec::new();
+        for path in paths {
+            let level = Level::load(&path)?;
+            if level.goal.is_none() {
+                return Err(LevelError::Parse {
+                    line: 0,
+                    message: format!("stage {} has no goal", path.display()),
+                });
+            }
+            stages.push(level);
+        }
+        Ok(Campaign { stages })
+    }
+
+    /// Loads the stages from the resources directory, or none at all if they
+    /// cannot be read.
+    pub fn load_or_default() -> Campaign {
+        Campaign::load(Campaign::dir()).unwrap_or_else(|e| {
+            eprintln!("Could not load the campaign: {}", e);
+            Campaign::default()
+        })
+    }
+}
+
+/// How far the player got in the campaign.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    /// Number of stages completed; the one after them is the next to play.
+    #[serde(default)]
+    pub completed: usize,
+}
+
+impl Progress {
+    /// Where the progress is kept, if the platform has a data directory.
+    pub fn path() -> Option<PathBuf> {
+        crate::project_dirs().map(|dirs| dirs.data_dir().join("campaign.toml"))
+    }
+
+    /// Loads the progress at `path`, starting from the first stage when there is
+    /// none yet or it cannot be read.
+    pub fn load_or_default(path: Option<&Path>) -> Progress {
+        store::load_or_default(path, "campaign progress")
+    }
+
+    /// Writes the progress to `path`, creating its directory if needed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), StoreError> {
+        store::save(path, self)
+    }
+
+    /// Whether stage number `stage`, counting from 0, may be played.
+    pub fn is_unlocked(&self, stage: usize) -> bool {
+        stage <= self.completed
+    }
+
+    /// Marks stage number `stage` as completed, unlocking the next one. Returns
+    /// whether that unlocked anything new.
+    pub fn complete(&mut self, stage: usize) -> bool {
+        if stage < self.completed {
+            return false;
+        }
+        self.completed = stage + 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completing_a_stage_unlocks_the_next() {
+        let mut progress = Progress::default();
+        assert!(progress.is_unlocked(0));
+        assert!(!progress.is_unlocked(1));
+        assert!(progress.complete(0));
+        assert!(progress.is_unlocked(1));
+        assert!(!progress.is_unlocked(2));
+        // Playing an earlier stage again does not lock anything.
+        assert!(progress.complete(1));
+        assert!(!progress.complete(0));
+        assert_eq!(progress.completed, 2);
+    }
+
+    #[test]
+    fn every_stage_has_a_goal() {
+        let campaign = Campaign::load(Campaign::dir()).unwrap();
+        assert!(!campaign.stages.is_empty());
+        assert!(campaign.stages.iter().all(|stage| stage.goal.is_some()));
+    }
+}
@@ -0,0 +1,219 @@
+//! Fixed-timestep ticking. The simulation only ever advances in whole ticks;
+//! `TickTimer` turns the time measured by a `Clock` into those ticks, so that the
+//! game runs at the same speed no matter how fast frames are drawn.
+
+use std::time::{Duration, Instant};
+
+/// A source of time. The game uses the wall clock, tests and tools can use a
+/// `ManualClock` and decide themselves when time passes.
+pub trait Clock {
+    /// Time elapsed since some fixed starting point.
+    fn now(&self) -> Duration;
+}
+
+/// The real wall clock.
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to.
+#[derive(Default)]
+pub struct ManualClock {
+    now: Duration,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock::default()
+    }
+
+    pub fn advance(&mut self, by: Duration) {
+        self.now += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now
+    }
+}
+
+/// What to do when more ticks are due than we would like to run at once, for
+/// example after the window was dragged or the machine stalled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CatchUp {
+    /// Run every tick that is due, however many there are.
+    All,
+    /// Run at most this many ticks per update and forget about the rest.
+    UpTo(u32),
+}
+
+impl Default for CatchUp {
+    fn default() -> Self {
+        CatchUp::UpTo(5)
+    }
+}
+
+/// Accumulates elapsed time and hands it out again as ticks.
+///
+/// Call `update` once per frame, then call `tick` with the current tick interval
+/// until it returns `false`, advancing the simulation once for every `true`.
+pub struct TickTimer<C: Clock = SystemClock> {
+    clock: C,
+    last: Duration,
+    accumulator: Duration,
+    catch_up: CatchUp,
+    /// Ticks handed out since the last `update`.
+    ticks_this_update: u32,
+    /// Game time passes `time_scale` times as fast as clock time.
+    time_scale: f64,
+}
+
+impl<C: Clock> TickTimer<C> {
+    pub fn new(clock: C, catch_up: CatchUp) -> Self {
+        let last = clock.now();
+        TickTimer {
+            clock,
+            last,
+            accumulator: Duration::from_millis(0),
+            catch_up,
+            ticks_this_update: 0,
+            time_scale: 1.0,
+        }
+    }
+
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Slows the game down (below 1.0) or fast-forwards it (above 1.0).
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    /// Adds the time passed since the last update to the accumulator.
+    pub fn update(&mut self) {
+        let now = self.clock.now();
+        let elapsed = now - self.last;
+        self.last = now;
+        // A time scale too large to measure just means a lot of catching up.
+        let scaled = Duration::try_from_secs_f64(elapsed.as_secs_f64() * self.time_scale).unwrap_or(Duration::MAX);
+        self.accumulator = self.accumulator.saturating_add(scaled);
+        self.ticks_this_update = 0;
+    }
+
+    /// Takes one tick of length `interval` out of the accumulator. Returns `false`
+    /// when not enough time has accumulated or the catch-up limit was reached.
+    pub fn tick(&mut self, interval: Duration) -> bool {
+        // A zero interval would hand out ticks forever.
+        let interval = interval.max(Duration::from_millis(1));
+        if self.accumulator < interval {
+            return false;
+        }
+        if let CatchUp::UpTo(max) = self.catch_up {
+            if self.ticks_this_update >= max {
+                // We are too far behind, drop the backlog instead of spiralling.
+                self.accumulator = Duration::from_millis(0);
+                return false;
+            }
+        }
+        self.accumulator -= interval;
+        self.ticks_this_update += 1;
+        true
+    }
+
+    /// Forgets any accumulated time, e.g. after a pause or a new game.
+    pub fn reset(&mut self) {
+        self.last = self.clock.now();
+        self.accumulator = Duration::from_millis(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(100);
+
+    /// Lets `elapsed` pass on the clock and counts the ticks handed out for it.
+    fn ticks_after(timer: &mut TickTimer<ManualClock>, elapsed: Duration) -> u32 {
+        timer.clock_mut().advance(elapsed);
+        timer.update();
+        let mut ticks = 0;
+        while timer.tick(TICK) {
+            ticks += 1;
+        }
+        ticks
+    }
+
+    #[test]
+    fn leftover_time_carries_over_to_the_next_update() {
+        let mut timer = TickTimer::new(ManualClock::new(), CatchUp::default());
+        assert_eq!(ticks_after(&mut timer, Duration::from_millis(250)), 2);
+        assert_eq!(ticks_after(&mut timer, Duration::from_millis(50)), 1);
+        assert_eq!(ticks_after(&mut timer, Duration::from_millis(50)), 0);
+    }
+
+    #[test]
+    fn catching_up_is_limited_unless_asked_for() {
+        let mut limited = TickTimer::new(ManualClock::new(), CatchUp::UpTo(5));
+        assert_eq!(ticks_after(&mut limited, Duration::from_secs(2)), 5);
+        // The rest of the backlog was dropped.
+        assert_eq!(ticks_after(&mut limited, Duration::from_millis(0)), 0);
+
+        let mut all = TickTimer::new(ManualClock::new(), CatchUp::All);
+        assert_eq!(ticks_after(&mut all, Duration::from_secs(2)), 20);
+    }
+
+    #[test]
+    fn time_scale_speeds_up_and_slows_down_the_game() {
+        let mut timer = TickTimer::new(ManualClock::new(), CatchUp::All);
+        timer.set_time_scale(2.0);
+        assert_eq!(ticks_after(&mut timer, Duration::from_secs(1)), 20);
+        timer.set_time_scale(0.5);
+        assert_eq!(ticks_after(&mut timer, Duration::from_secs(1)), 5);
+        timer.set_time_scale(-1.0);
+        assert_eq!(timer.time_scale(), 0.0);
+        assert_eq!(ticks_after(&mut timer, Duration::from_secs(1)), 0);
+    }
+
+    #[test]
+    fn huge_time_scale_only_catches_up() {
+        let mut timer = TickTimer::new(ManualClock::new(), CatchUp::UpTo(5));
+        timer.set_time_scale(1e300);
+        assert_eq!(ticks_after(&mut timer, Duration::from_secs(1)), 5);
+        assert_eq!(ticks_after(&mut timer, Duration::from_secs(1)), 5);
+    }
+
+    #[test]
+    fn reset_forgets_the_time_passed() {
+        let mut timer = TickTimer::new(ManualClock::new(), CatchUp::All);
+        timer.clock_mut().advance(Duration::from_secs(1));
+        timer.reset();
+        assert_eq!(ticks_after(&mut timer, Duration::from_millis(0)), 0);
+    }
+}
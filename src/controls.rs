@@ -0,0 +1,367 @@
+//! What the player can do, and which keys do it.
+//!
+//! Input is not handled key by key but by `Action`: the front-end looks up the
+//! action bound to a pressed key and reacts to that. The second player of a
+//! versus match steers with actions of their own; playing alone, those steer
+//! the one snake too. Every action can have
+//! several keys, and a key does at most one action. Keys are named the way ggez
+//! names its `KeyCode`s, e.g. `Up`, `W`, `Return` or `Escape`.
+//!
+//! Gamepads are not rebindable: the D-pad and the left stick steer, Start
+//! pauses and the face buttons confirm and cancel. `Stick` turns the position
+//! of an analog stick into directions.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::Direction;
+
+/// Something the player can do with a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    TurnUp,
+    TurnDown,
+    TurnLeft,
+    TurnRight,
+    Player2Up,
+    Player2Down,
+    Player2Left,
+    Player2Right,
+    Pause,
+    ToggleMusic,
+    /// Choose the selected menu item.
+    Confirm,
+    /// Leave a menu or screen.
+    Cancel,
+    /// Quit the game right away.
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 13] = [
+        Action::TurnUp,
+        Action::TurnDown,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Player2Up,
+        Action::Player2Down,
+        Action::Player2Left,
+        Action::Player2Right,
+        Action::Pause,
+        Action::ToggleMusic,
+        Action::Confirm,
+        Action::Cancel,
+        Action::Quit,
+    ];
+
+    /// The name used in the settings file, e.g. `turn_up`.
+    pub fn key(self) -> &'static str {
+        match self {
+            Action::TurnUp => "turn_up",
+            Action::TurnDown => "turn_down",
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::Player2Up => "player2_up",
+            Action::Player2Down => "player2_down",
+            Action::Player2Left => "player2_left",
+            Action::Player2Right => "player2_right",
+            Action::Pause => "pause",
+            Action::ToggleMusic => "toggle_music",
+            Action::Confirm => "confirm",
+            Action::Cancel => "cancel",
+            Action::Quit => "quit",
+        }
+    }
+
+    /// The name shown to the player.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::TurnUp => "Turn up",
+            Action::TurnDown => "Turn down",
+            Action::TurnLeft => "Turn left",
+            Action::TurnRight => "Turn right",
+            Action::Player2Up => "Player 2 up",
+            Action::Player2Down => "Player 2 down",
+            Action::Player2Left => "Player 2 left",
+            Action::Player2Right => "Player 2 right",
+            Action::Pause => "Pause",
+            Action::ToggleMusic => "Toggle music",
+            Action::Confirm => "Confirm",
+            Action::Cancel => "Cancel",
+            Action::Quit => "Quit",
+        }
+    }
+
+    /// The action that turns the snake of `player` (0 or 1) to `direction`.
+    pub fn turn(player: usize, direction: Direction) -> Action {
+        match (player, direction) {
+            (0, Direction::Up) => Action::TurnUp,
+            (0, Direction::Down) => Action::TurnDown,
+            (0, Direction::Left) => Action::TurnLeft,
+            (0, Direction::Right) => Action::TurnRight,
+            (_, Direction::Up) => Action::Player2Up,
+            (_, Direction::Down) => Action::Player2Down,
+            (_, Direction::Left) => Action::Player2Left,
+            (_, Direction::Right) => Action::Player2Right,
+        }
+    }
+
+    /// The direction a turn action turns the snake to.
+    pub fn direction(self) -> Option<Direction> {
+        match self {
+            Action::TurnUp | Action::Player2Up => Some(Direction::Up),
+            Action::TurnDown | Action::Player2Down => Some(Direction::Down),
+            Action::TurnLeft | Action::Player2Left => Some(Direction::Left),
+            Action::TurnRight | Action::Player2Right => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    /// The player whose snake a turn action steers in a versus match.
+    pub fn player(self) -> usize {
+        match self {
+            Action::Player2Up | Action::Player2Down | Action::Player2Left | Action::Player2Right => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Parses the name used in the settings file. The names the settings used
+/// before there were actions (`up`, `music`, ...) are accepted too.
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "up" => Ok(Action::TurnUp),
+            "down" => Ok(Action::TurnDown),
+            "left" => Ok(Action::TurnLeft),
+            "right" => Ok(Action::TurnRight),
+            "music" => Ok(Action::ToggleMusic),
+            _ => Action::ALL
+                .iter()
+                .find(|action| action.key() == s)
+                .copied()
+                .ok_or(format!("unknown action `{}`", s)),
+        }
+    }
+}
+
+/// The keys bound to every action.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "BTreeMap<String, Vec<String>>", into = "BTreeMap<String, Vec<String>>")]
+pub struct Bindings {
+    keys: BTreeMap<Action, Vec<String>>,
+}
+
+impl Default for Bindings {
+    /// Arrow keys, WASD and the vi keys HJKL all steer. In a versus match the
+    /// first player has WASD and HJKL, the second one the arrow keys.
+    fn default() -> Self {
+        let mut bindings = Bindings { keys: BTreeMap::new() };
+        let defaults: [(Action, &[&str]); 13] = [
+            (Action::TurnUp, &["W", "K"]),
+            (Action::TurnDown, &["S", "J"]),
+            (Action::TurnLeft, &["A", "H"]),
+            (Action::TurnRight, &["D", "L"]),
+            (Action::Player2Up, &["Up"]),
+            (Action::Player2Down, &["Down"]),
+            (Action::Player2Left, &["Left"]),
+            (Action::Player2Right, &["Right"]),
+            (Action::Pause, &["P"]),
+            (Action::ToggleMusic, &["M"]),
+            (Action::Confirm, &["Return", "NumpadEnter", "Space"]),
+            (Action::Cancel, &["Escape"]),
+            (Action::Quit, &["Q"]),
+        ];
+        for (action, keys) in defaults.iter() {
+            for key in keys.iter() {
+                bindings.keys.entry(*action).or_default().push(key.to_string());
+            }
+        }
+        bindings
+    }
+}
+
+/// Actions missing from the map keep their default keys.
+impl TryFrom<BTreeMap<String, Vec<String>>> for Bindings {
+    type Error = String;
+
+    fn try_from(keys: BTreeMap<String, Vec<String>>) -> Result<Self, Self::Error> {
+        let defaults = Bindings::default();
+        let mut bindings = defaults.clone();
+        for (action, keys) in keys {
+            let action: Action = action.parse()?;
+            bindings.keys.insert(action, Vec::new());
+            for key in keys {
+                bindings.bind(action, &key);
+            }
+        }
+        // Without these the menus could not be used at all.
+        for action in [Action::Confirm, Action::Cancel].iter() {
+            if bindings.keys(*action).is_empty() {
+                for key in defaults.keys(*action) {
+                    for keys in bindings.keys.values_mut() {
+                        keys.retain(|bound| bound != key);
+                    }
+                }
+                bindings.keys.insert(*action, defaults.keys(*action).to_vec());
+            }
+        }
+        Ok(bindings)
+    }
+}
+
+impl From<Bindings> for BTreeMap<String, Vec<String>> {
+    fn from(bindings: Bindings) -> Self {
+        bindings
+            .keys
+            .into_iter()
+            .map(|(action, keys)| (action.key().to_string(), keys))
+            .collect()
+    }
+}
+
+impl Bindings {
+    /// The keys bound to `action`.
+    pub fn keys(&self, action: Action) -> &[String] {
+        self.keys.get(&action).map_or(&[], |keys| keys.as_slice())
+    }
+
+    /// The action bound to `key`, if any.
+    pub fn action(&self, key: &str) -> Option<Action> {
+        self.keys
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|bound| bound == key))
+            .map(|(action, _)| *action)
+    }
+
+    /// Binds `key` to `action`, taking it away from any other action. Confirm
+    /// and Cancel always keep one key, so that the menus can still be used;
+    /// returns `false` if `key` is the last key of one of them.
+    pub fn bind(&mut self, action: Action, key: &str) -> bool {
+        match self.action(key) {
+            Some(bound) if bound == action => return true,
+            Some(bound) if Bindings::is_essential(bound) && self.keys(bound).len() == 1 => return false,
+            _ => {}
+        }
+        for keys in self.keys.values_mut() {
+            keys.retain(|bound| bound != key);
+        }
+        self.keys.entry(action).or_default().push(key.to_string());
+        true
+    }
+
+    /// Removes the key last bound to `action`, unless it is the last key of
+    /// Confirm or Cancel.
+    pub fn unbind_last(&mut self, action: Action) {
+        let keep = if Bindings::is_essential(action) { 1 } else { 0 };
+        let keys = self.keys.entry(action).or_default();
+        if keys.len() > keep {
+            keys.pop();
+        }
+    }
+
+    fn is_essential(action: Action) -> bool {
+        action == Action::Confirm || action == Action::Cancel
+    }
+}
+
+/// How far a stick has to be pushed before it counts, unless the settings say otherwise.
+pub const DEFAULT_DEADZONE: f32 = 0.3;
+
+/// Turns the position of an analog stick into directions. A stick is pushed in
+/// the direction of the axis it is pushed furthest along, once it leaves the
+/// deadzone in the middle; it reports each new direction just once, like a key
+/// that is pressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stick {
+    x: f32,
+    y: f32,
+    deadzone: f32,
+    direction: Option<Direction>,
+}
+
+impl Stick {
+    pub fn new(deadzone: f32) -> Self {
+        Stick {
+            x: 0.0,
+            y: 0.0,
+            deadzone,
+            direction: None,
+        }
+    }
+
+    /// The stick moved along the horizontal axis, right is positive. Returns
+    /// the direction it now points to if that changed.
+    pub fn move_x(&mut self, x: f32) -> Option<Direction> {
+        self.x = x;
+        self.update()
+    }
+
+    /// The stick moved along the vertical axis, up is positive. Returns the
+    /// direction it now points to if that changed.
+    pub fn move_y(&mut self, y: f32) -> Option<Direction> {
+        self.y = y;
+        self.update()
+    }
+
+    fn update(&mut self) -> Option<Direction> {
+        let direction = if self.x.abs().max(self.y.abs()) < self.deadzone {
+            None
+        } else if self.x.abs() > self.y.abs() {
+            Some(if self.x > 0.0 { Direction::Right } else { Direction::Left })
+        } else {
+            Some(if self.y > 0.0 { Direction::Up } else { Direction::Down })
+        };
+        if direction == self.direction {
+            return None;
+        }
+        self.direction = direction;
+        direction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_a_key_takes_it_away_from_other_actions() {
+        let mut bindings = Bindings::default();
+        assert!(bindings.bind(Action::Pause, "W"));
+        assert_eq!(bindings.action("W"), Some(Action::Pause));
+        assert_eq!(bindings.keys(Action::TurnUp), ["K"]);
+        assert_eq!(bindings.keys(Action::Pause), ["P", "W"]);
+        // Binding a key to the action it already has changes nothing.
+        assert!(bindings.bind(Action::Pause, "W"));
+        assert_eq!(bindings.keys(Action::Pause), ["P", "W"]);
+    }
+
+    #[test]
+    fn the_last_key_of_cancel_cannot_be_taken() {
+        let mut bindings = Bindings::default();
+        assert!(!bindings.bind(Action::Pause, "Escape"));
+        assert_eq!(bindings.action("Escape"), Some(Action::Cancel));
+        // Confirm has keys to spare.
+        assert!(bindings.bind(Action::Pause, "Space"));
+        bindings.unbind_last(Action::Cancel);
+        assert_eq!(bindings.keys(Action::Cancel), ["Escape"]);
+    }
+
+    #[test]
+    fn stick_ignores_the_deadzone_and_reports_each_direction_once() {
+        let mut stick = Stick::new(0.3);
+        assert_eq!(stick.move_x(0.2), None);
+        assert_eq!(stick.move_x(0.8), Some(Direction::Right));
+        assert_eq!(stick.move_x(0.9), None);
+        // Pushed further up than right, so it points up now.
+        assert_eq!(stick.move_y(0.95), Some(Direction::Up));
+        assert_eq!(stick.move_y(-0.1), Some(Direction::Right));
+        assert_eq!(stick.move_x(0.0), None);
+        assert_eq!(stick.move_x(-0.5), Some(Direction::Left));
+    }
+}
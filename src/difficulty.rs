@@ -0,0 +1,243 @@
+//! How fast the game runs, and how much faster it gets as the snakes eat.
+//!
+//! The time between two ticks follows a speed curve of the number of pieces of
+//! food eaten, and never drops below a minimum. A curve is written as its name
+//! followed by its numbers in milliseconds, then `min` and the minimum, which
+//! is how it appears in settings, replays and on the command line of
+//! `snake-sim`:
+//!
+//! * `constant <start>` - always `start`.
+//! * `stepwise <start> <step> <every>` - `step` less for every `every` pieces.
+//! * `sqrt <start> <factor>` - `start - factor * sqrt(pieces)`.
+//! * `capped <start> <step> <cap>` - `step` less for every piece, up to `cap`
+//!   pieces.
+//!
+//! For example `sqrt 100 8 min 30`, the curve of `Difficulty::Normal`. The
+//! presets are:
+//!
+//! | difficulty | curve                        |
+//! |------------|------------------------------|
+//! | `easy`     | `sqrt 130 6 min 60`          |
+//! | `normal`   | `sqrt 100 8 min 30`          |
+//! | `hard`     | `stepwise 80 5 4 min 30`     |
+//! | `insane`   | `capped 55 1 30 min 25`      |
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// The shape of a speed curve, in milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    Constant { start: u32 },
+    Stepwise { start: u32, step: u32, every: u32 },
+    Sqrt { start: u32, factor: u32 },
+    Capped { start: u32, step: u32, cap: u32 },
+}
+
+/// How long a tick lasts depending on the food eaten.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SpeedCurve {
+    pub curve: Curve,
+    /// The game never runs faster than one tick in this many milliseconds.
+    pub min: u32,
+}
+
+impl SpeedCurve {
+    /// The tick interval after `eaten` pieces of food.
+    pub fn interval(&self, eaten: u32) -> Duration {
+        let millis = match self.curve {
+            Curve::Constant { start } => start as f32,
+            Curve::Stepwise { start, step, every } => start as f32 - step.saturating_mul(eaten / every.max(1)) as f32,
+            Curve::Sqrt { start, factor } => start as f32 - factor as f32 * (eaten as f32).sqrt(),
+            Curve::Capped { start, step, cap } => start as f32 - step.saturating_mul(eaten.min(cap)) as f32,
+        };
+        Duration::from_millis(millis.max(self.min.max(1) as f32) as u64)
+    }
+}
+
+impl Default for SpeedCurve {
+    fn default() -> Self {
+        Difficulty::Normal.speed()
+    }
+}
+
+impl fmt::Display for SpeedCurve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.curve {
+            Curve::Constant { start } => write!(f, "constant {}", start)?,
+            Curve::Stepwise { start, step, every } => write!(f, "stepwise {} {} {}", start, step, every)?,
+            Curve::Sqrt { start, factor } => write!(f, "sqrt {} {}", start, factor)?,
+            Curve::Capped { start, step, cap } => write!(f, "capped {} {} {}", start, step, cap)?,
+        }
+        write!(f, " min {}", self.min)
+    }
+}
+
+impl FromStr for SpeedCurve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let name = fields.next().unwrap_or("");
+        let mut number = || -> Result<u32, String> {
+            match fields.next().map(str::parse) {
+                Some(Ok(n)) => Ok(n),
+                _ => Err(format!("expected a curve like `sqrt 100 8 min 30`, found `{}`", s)),
+            }
+        };
+        let curve = match name {
+            "constant" => Curve::Constant { start: number()? },
+            "stepwise" => Curve::Stepwise {
+                start: number()?,
+                step: number()?,
+                every: number()?,
+            },
+            "sqrt" => Curve::Sqrt {
+                start: number()?,
+                factor: number()?,
+            },
+            "capped" => Curve::Capped {
+                start: number()?,
+                step: number()?,
+                cap: number()?,
+            },
+            other => return Err(format!("unknown speed curve `{}`", other)),
+        };
+        let min = match (fields.next(), fields.next().map(str::parse)) {
+            (Some("min"), Some(Ok(min))) if min > 0 => min,
+            _ => return Err(format!("`{}` needs a minimum like `min 30`", s)),
+        };
+        match fields.next() {
+            Some(extra) => Err(format!("unexpected `{}` after the minimum", extra)),
+            None => Ok(SpeedCurve { curve, min }),
+        }
+    }
+}
+
+impl TryFrom<String> for SpeedCurve {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SpeedCurve> for String {
+    fn from(speed: SpeedCurve) -> String {
+        speed.to_string()
+    }
+}
+
+/// The speed curves to choose from in the options.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Insane,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Insane];
+
+    pub fn speed(self) -> SpeedCurve {
+        match self {
+            Difficulty::Easy => SpeedCurve {
+                curve: Curve::Sqrt { start: 130, factor: 6 },
+                min: 60,
+            },
+            Difficulty::Normal => SpeedCurve {
+                curve: Curve::Sqrt { start: 100, factor: 8 },
+                min: 30,
+            },
+            Difficulty::Hard => SpeedCurve {
+                curve: Curve::Stepwise {
+                    start: 80,
+                    step: 5,
+                    every: 4,
+                },
+                min: 30,
+            },
+            Difficulty::Insane => SpeedCurve {
+                curve: Curve::Capped {
+                    start: 55,
+                    step: 1,
+                    cap: 30,
+                },
+                min: 25,
+            },
+        }
+    }
+
+    /// The preset with this speed curve, if there is one.
+    pub fn of(speed: SpeedCurve) -> Option<Difficulty> {
+        Difficulty::ALL.iter().cloned().find(|difficulty| difficulty.speed() == speed)
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Insane => "insane",
+        })
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Difficulty::ALL
+            .iter()
+            .find(|difficulty| difficulty.to_string() == s)
+            .cloned()
+            .ok_or_else(|| format!("unknown difficulty `{}`", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        let curves = Difficulty::ALL.iter().map(|difficulty| difficulty.speed()).chain(vec![
+            SpeedCurve {
+                curve: Curve::Constant { start: 90 },
+                min: 90,
+            },
+            SpeedCurve {
+                curve: Curve::Capped {
+                    start: 120,
+                    step: 5,
+                    cap: 10,
+                },
+                min: 40,
+            },
+        ]);
+        for curve in curves {
+            assert_eq!(curve.to_string().parse(), Ok(curve));
+        }
+        assert!("sqrt 100 8".parse::<SpeedCurve>().is_err());
+        assert!("sqrt 100 8 min 0".parse::<SpeedCurve>().is_err());
+        assert!("wobbly 100 min 30".parse::<SpeedCurve>().is_err());
+    }
+
+    #[test]
+    fn never_faster_than_the_minimum() {
+        let curve: SpeedCurve = "stepwise 100 10 1 min 30".parse().unwrap();
+        assert_eq!(curve.interval(0), Duration::from_millis(100));
+        assert_eq!(curve.interval(3), Duration::from_millis(70));
+        assert_eq!(curve.interval(50), Duration::from_millis(30));
+    }
+}
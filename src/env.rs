@@ -0,0 +1,333 @@
+//! A reinforcement-learning environment in the style of OpenAI Gym.
+//!
+//! `Env` plays the exact rules of the game headless, one tick per `step`:
+//!
+//! ```no_run
+//! use snake_remix::env::Env;
+//! use snake_remix::level::Level;
+//! use snake_remix::sim::{Direction, Grid};
+//!
+//! let mut env = Env::new(Level::open(Grid::default()));
+//! let mut observation = env.reset(42);
+//! loop {
+//!     // An agent would look at `observation` here.
+//!     let step = env.step(Direction::Right);
+//!     observation = step.observation;
+//!     if step.done {
+//!         break;
+//!     }
+//! }
+//! ```
+//!
+//! Every observation comes in two forms. `grid` is a tensor of shape
+//! `[CHANNELS, height, width]`, row-major, with a 1.0 wherever the channel
+//! applies:
+//!
+//! * 0 - walls
+//! * 1 - the agent's head
+//! * 2 - the agent's body
+//! * 3 - food
+//! * 4 - other snakes
+//!
+//! `features` is a short vector of `FEATURES` values:
+//!
+//! * 0, 1 - head position, x and y, scaled to 0..1
+//! * 2, 3 - position of the nearest food relative to the head, x and y,
+//!   scaled to -1..1 and taking the short way round on a board that wraps
+//! * 4..8 - danger flags: 1.0 if the action up, down, left or right kills the
+//!   snake on the next step
+//! * 8..12 - the direction the snake moved in last, one-hot: up, down, left, right
+//! * 12 - length of the snake as a fraction of the board
+
+use crate::bot::View;
+use crate::level::Level;
+use crate::sim::{Ate, Boundary, Direction, Event, GameStates, GridPosition, Input, Rules, Simulation};
+
+/// Number of channels of `Observation::grid`.
+pub const CHANNELS: usize = 5;
+/// Length of `Observation::features`.
+pub const FEATURES: usize = 13;
+
+const DIRECTIONS: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+/// What the agent sees of the board.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    pub width: usize,
+    pub height: usize,
+    /// `CHANNELS` planes of `height` rows of `width` cells.
+    pub grid: Vec<f32>,
+    pub features: Vec<f32>,
+}
+
+/// Extra information about a step, not meant to be learned from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Info {
+    pub points: i32,
+    pub length: usize,
+    pub tick: u64,
+    /// What the snake ran into on this step, if anything.
+    pub ate: Option<Ate>,
+    /// The snake filled the whole board, or lived through a time attack.
+    pub won: bool,
+    /// The episode was cut off after `Env::max_ticks` ticks.
+    pub timed_out: bool,
+}
+
+/// The result of `Env::step`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub observation: Observation,
+    pub reward: f32,
+    /// Whether the episode is over; call `reset` to start the next one.
+    pub done: bool,
+    pub info: Info,
+}
+
+/// What the agent is rewarded with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rewards {
+    pub food: f32,
+    pub death: f32,
+    /// Given on every step, e.g. a small negative value to hurry the agent up.
+    pub step: f32,
+    pub won: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Rewards {
+            food: 1.0,
+            death: -1.0,
+            step: 0.0,
+            won: 10.0,
+        }
+    }
+}
+
+/// The game as an environment for a single agent steering the first snake.
+pub struct Env {
+    pub level: Level,
+    /// The rules episodes are played under, e.g. with power-ups.
+    pub rules: Rules,
+    pub rewards: Rewards,
+    /// Episodes end after this many ticks, so that an agent going round in
+    /// circles cannot stall training.
+    pub max_ticks: u64,
+    sim: Simulation,
+}
+
+impl Env {
+    pub fn new(level: Level) -> Self {
+        Env {
+            sim: Simulation::new(level.clone(), 0),
+            level,
+            rules: Rules::default(),
+            rewards: Rewards::default(),
+            max_ticks: 10_000,
+        }
+    }
+
+    /// The simulation being played, e.g. to draw it.
+    pub fn simulation(&self) -> &Simulation {
+        &self.sim
+    }
+
+    /// Starts a new episode. The same seed always gives the same food sequence.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.sim = Simulation::with_rules(self.level.clone(), seed, 1, self.rules);
+        self.observation()
+    }
+
+    /// Turns the snake towards `action` (turning back into the body is ignored,
+    /// like in the game) and plays one tick.
+    pub fn step(&mut self, action: Direction) -> Step {
+        let already_over = self.sim.game_states != GameStates::GameOn;
+        let events = self.sim.step(&[Input::Turn { snake: 0, dir: action }]);
+
+        let ate = events.iter().find_map(|event| match event {
+            Event::Ate { snake: 0, ate } => Some(*ate),
+            _ => None,
+        });
+        let won = self.sim.game_states == GameStates::Won;
+        let died = events.iter().any(|event| matches!(event, Event::Died { snake: 0, .. }));
+        let timed_out = !won && !died && self.sim.tick >= self.max_ticks;
+
+        let mut reward = if already_over { 0.0 } else { self.rewards.step };
+        if ate == Some(Ate::Food) {
+            reward += self.rewards.food;
+        }
+        if died {
+            reward += self.rewards.death;
+        }
+        if won {
+            reward += self.rewards.won;
+        }
+
+        let snake = self.sim.snake();
+        Step {
+            observation: self.observation(),
+            reward,
+            done: already_over || won || died || timed_out,
+            info: Info {
+                points: snake.points,
+                length: snake.length(),
+                tick: self.sim.tick,
+                ate,
+                won,
+                timed_out,
+            },
+        }
+    }
+
+    /// The board as the agent sees it right now.
+    pub fn observation(&self) -> Observation {
+        let sim = &self.sim;
+        let grid = sim.grid();
+        let (width, height) = (grid.width as usize, grid.height as usize);
+        let mut tensor = vec![0.0; CHANNELS * width * height];
+        let mut set = |channel: usize, pos: GridPosition| {
+            if grid.contains(pos) {
+                tensor[(channel * height + pos.y as usize) * width + pos.x as usize] = 1.0;
+            }
+        };
+        for wall in sim.level.walls() {
+            set(0, wall);
+        }
+        for (i, snake) in sim.snakes.iter().enumerate() {
+            let (head, body) = if i == 0 { (1, 2) } else { (4, 4) };
+            set(head, snake.head.pos);
+            for seg in snake.body.iter() {
+                set(body, seg.pos);
+            }
+        }
+        for food in &sim.foods {
+            set(3, food.pos);
+        }
+
+        let snake = sim.snake();
+        let head = snake.head.pos;
+        // The offset along one axis, the short way round on a wrapping board.
+        let delta = |from: i16, to: i16, size: i16| {
+            let d = to - from;
+            let d = match grid.boundary {
+                Boundary::Wrap if d > size / 2 => d - size,
+                Boundary::Wrap if d < -size / 2 => d + size,
+                _ => d,
+            };
+            d as f32 / size as f32
+        };
+        let view = View::new(sim, 0);
+        // With no food on the board the snake is told it is right on top of it.
+        let food = sim
+            .foods
+            .iter()
+            .map(|food| food.pos)
+            .min_by_key(|pos| view.distance(head, *pos))
+            .unwrap_or(head);
+        let mut features = vec![
+            head.x as f32 / grid.width as f32,
+            head.y as f32 / grid.height as f32,
+            delta(head.x, food.x, grid.width),
+            delta(head.y, food.y, grid.height),
+        ];
+        // Turning back is ignored, so the snake goes straight on instead.
+        features.extend(DIRECTIONS.iter().map(|dir| {
+            let dir = if *dir == snake.last_update_dir.inverse() { snake.last_update_dir } else { *dir };
+            if view.is_blocked(view.next(head, dir)) {
+                1.0
+            } else {
+                0.0
+            }
+        }));
+        features.extend(DIRECTIONS.iter().map(|dir| if *dir == snake.last_update_dir { 1.0 } else { 0.0 }));
+        features.push(snake.length() as f32 / (width * height) as f32);
+
+        Observation {
+            width,
+            height,
+            grid: tensor,
+            features,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{Food, Grid, Segment};
+
+    /// The danger flags of an observation: up, down, left, right.
+    fn danger(observation: &Observation) -> &[f32] {
+        &observation.features[4..8]
+    }
+
+    #[test]
+    fn walls_are_dangerous() {
+        let level = Level::parse("boundary solid\nmap\n......\n.....>\n......\n......\n").unwrap();
+        let mut env = Env::new(level);
+        let observation = env.reset(1);
+        // Turning back is going straight on, off the board.
+        assert_eq!(danger(&observation), [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn the_body_is_dangerous() {
+        let mut env = Env::new(Level::open(Grid::new(8, 6)));
+        env.reset(1);
+        let snake = &mut env.sim.snakes[0];
+        snake.head = Segment::new(GridPosition::new(2, 3));
+        snake.body = [(1, 3), (1, 2), (2, 2)]
+            .iter()
+            .map(|&pos| Segment::new(GridPosition::from(pos)))
+            .collect();
+        env.sim.refresh();
+        let observation = env.observation();
+        assert_eq!(danger(&observation), [1.0, 0.0, 0.0, 0.0]);
+        let cell = |channel: usize, x: usize, y: usize| observation.grid[(channel * 6 + y) * 8 + x];
+        assert_eq!(cell(1, 2, 3), 1.0);
+        assert_eq!(cell(2, 2, 2), 1.0);
+        assert_eq!(cell(2, 2, 3), 0.0);
+    }
+
+    #[test]
+    fn eating_is_rewarded() {
+        let mut env = Env::new(Level::open(Grid::new(8, 6)));
+        env.reset(1);
+        let ahead = GridPosition::new(3, 3);
+        env.sim.foods = vec![Food::new(ahead)];
+        env.sim.refresh();
+        let step = env.step(Direction::Right);
+        assert_eq!(step.info.ate, Some(Ate::Food));
+        assert_eq!(step.reward, env.rewards.food);
+        assert!(!step.done);
+    }
+
+    #[test]
+    fn dying_is_punished_and_ends_the_episode() {
+        let level = Level::parse("boundary solid\nmap\n......\n.....>\n......\n......\n").unwrap();
+        let mut env = Env::new(level);
+        env.reset(1);
+        let step = env.step(Direction::Right);
+        assert_eq!(step.info.ate, Some(Ate::Wall));
+        assert_eq!(step.reward, env.rewards.death);
+        assert!(step.done);
+        // Stepping on after the end changes nothing.
+        let after = env.step(Direction::Up);
+        assert_eq!(after.reward, 0.0);
+        assert!(after.done);
+    }
+
+    #[test]
+    fn reset_with_the_same_seed_plays_the_same_episode() {
+        let mut env = Env::new(Level::open(Grid::new(10, 8)));
+        let mut play = |seed: u64| -> (Observation, Vec<Step>) {
+            let first = env.reset(seed);
+            let steps = (0..60).map(|i| env.step(DIRECTIONS[i / 5 % DIRECTIONS.len()])).collect();
+            (first, steps)
+        };
+        let a = play(9);
+        let b = play(9);
+        assert_eq!(a, b);
+    }
+}
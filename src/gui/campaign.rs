@@ -0,0 +1,72 @@
+//! The campaign screen, where the next stage is picked.
+
+use ggez::{Context, GameResult};
+
+use snake_remix::controls::Action;
+
+use super::game::GameScreen;
+use super::menu::{Choice, Menu};
+use super::{draw_centered, label, App, Screen, Transition};
+
+/// Lists the stages of the campaign with their goals. Stages that are still
+/// locked are shown, but cannot be picked.
+pub struct CampaignScreen {
+    menu: Menu,
+}
+
+impl CampaignScreen {
+    pub fn new(app: &App) -> Self {
+        let mut menu = Menu::new(CampaignScreen::items(app));
+        // The first stage not completed yet, or the last one once all are.
+        menu.selected = app.progress.completed.min(app.campaign.stages.len().saturating_sub(1));
+        CampaignScreen { menu }
+    }
+
+    /// One item per stage, then `Back`.
+    fn items(app: &App) -> Vec<String> {
+        let mut items: Vec<String> = app
+            .campaign
+            .stages
+            .iter()
+            .enumerate()
+            .map(|(i, stage)| match stage.goal {
+                Some(goal) if app.progress.is_unlocked(i) => format!("{}. {} - {}", i + 1, stage.name, goal),
+                _ => format!("{}. Locked", i + 1),
+            })
+            .collect();
+        items.push("Back".to_string());
+        items
+    }
+}
+
+impl Screen for CampaignScreen {
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        self.menu.items = CampaignScreen::items(app);
+        let y = 0.15 * app.screen_size.y;
+        draw_centered(ctx, app, &label("CAMPAIGN".to_string(), 60.0), y)?;
+        let stages = app.campaign.stages.len();
+        let done = app.progress.completed.min(stages);
+        draw_centered(ctx, app, &label(format!("{} of {} stages completed", done, stages), 24.0), y + 70.0)?;
+        self.menu.draw(ctx, app, y + 120.0)
+    }
+
+    fn action(&mut self, ctx: &mut Context, app: &mut App, action: Action) -> Transition {
+        match self.menu.action(action) {
+            Choice::Chosen(i) if i < app.campaign.stages.len() => {
+                if !app.progress.is_unlocked(i) {
+                    return Transition::None;
+                }
+                match app.play_stage(ctx, i) {
+                    Ok(()) => Transition::Replace(Box::new(GameScreen::new())),
+                    Err(e) => {
+                        eprintln!("Could not start stage {}: {}", i + 1, e);
+                        Transition::None
+                    }
+                }
+            }
+            Choice::Chosen(_) | Choice::Back => Transition::Pop,
+            Choice::Quit => Transition::Quit,
+            _ => Transition::None,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! The controls screen, where keys are bound to actions.
+
+use ggez::event::KeyCode;
+use ggez::{Context, GameResult};
+
+use snake_remix::controls::{Action, Bindings};
+
+use super::menu::{Choice, Menu};
+use super::{action, draw_centered, key_name, label, text, App, Screen, Transition, YELLOW};
+
+/// Lists every action with its keys. Choosing an action waits for a key to add
+/// to it, Backspace removes the key added last. Every change is saved right away.
+pub struct ControlsScreen {
+    menu: Menu,
+    /// The action the next key pressed is bound to.
+    capturing: Option<Action>,
+    /// Tells the player why the last key could not be bound.
+    message: Option<String>,
+}
+
+impl ControlsScreen {
+    pub fn new() -> Self {
+        ControlsScreen {
+            menu: Menu::new(Vec::new()),
+            capturing: None,
+            message: None,
+        }
+    }
+
+    fn items(bindings: &Bindings) -> Vec<String> {
+        let mut items: Vec<String> = Action::ALL
+            .iter()
+            .map(|action| format!("{}: {}", action.name(), bindings.keys(*action).join(", ")))
+            .collect();
+        items.push("Reset to defaults".to_string());
+        items.push("Back".to_string());
+        items
+    }
+
+    /// The key was pressed while waiting for a key to bind.
+    fn capture(&mut self, app: &mut App, action: Action, keycode: KeyCode) {
+        self.capturing = None;
+        if keycode == KeyCode::Back {
+            return;
+        }
+        let key = key_name(keycode);
+        if app.settings.keys.bind(action, &key) {
+            app.save_settings();
+        } else {
+            let bound = app.settings.keys.action(&key).map_or("", |bound| bound.name());
+            self.message = Some(format!("{} is the only key left for {}", key, bound));
+        }
+    }
+}
+
+impl Screen for ControlsScreen {
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        self.menu.items = ControlsScreen::items(&app.settings.keys);
+        let y = 0.1 * app.screen_size.y;
+        draw_centered(ctx, app, &label("CONTROLS".to_string(), 60.0), y)?;
+        let hint = match (self.capturing, &self.message) {
+            (Some(action), _) => text(format!("Press a key for {}, Backspace to cancel", action.name()), 24.0, YELLOW),
+            (None, Some(message)) => text(message.clone(), 24.0, YELLOW),
+            (None, None) => label("Choose an action to add a key, Backspace removes one".to_string(), 24.0),
+        };
+        draw_centered(ctx, app, &hint, y + 70.0)?;
+        self.menu.draw(ctx, app, y + 120.0)
+    }
+
+    fn key_down(&mut self, ctx: &mut Context, app: &mut App, keycode: KeyCode) -> Transition {
+        self.message = None;
+        if let Some(action) = self.capturing {
+            self.capture(app, action, keycode);
+            return Transition::None;
+        }
+        if keycode == KeyCode::Back {
+            if let Some(action) = Action::ALL.get(self.menu.selected) {
+                app.settings.keys.unbind_last(*action);
+                app.save_settings();
+            }
+            return Transition::None;
+        }
+        match action(app, keycode) {
+            Some(action) => self.action(ctx, app, action),
+            None => Transition::None,
+        }
+    }
+
+    fn action(&mut self, _ctx: &mut Context, app: &mut App, action: Action) -> Transition {
+        // Only keys can be bound, a gamepad button gives up waiting for one.
+        if self.capturing.take().is_some() {
+            return Transition::None;
+        }
+        let reset = Action::ALL.len();
+        let back = reset + 1;
+        match self.menu.action(action) {
+            Choice::Chosen(item) if item == reset => {
+                app.settings.keys = Bindings::default();
+                app.save_settings();
+            }
+            Choice::Chosen(item) if item == back => return Transition::Pop,
+            Choice::Chosen(item) => self.capturing = Some(Action::ALL[item]),
+            Choice::Back => return Transition::Pop,
+            Choice::Quit => return Transition::Quit,
+            _ => {}
+        }
+        Transition::None
+    }
+}
@@ -0,0 +1,111 @@
+//! The demo played by bots when nobody is playing, like on an arcade cabinet.
+
+use ggez::event::KeyCode;
+use ggez::{Context, GameResult};
+
+use std::time::{Duration, Instant};
+
+use snake_remix::bot::{Bot, Driver};
+use snake_remix::controls::Action;
+use snake_remix::sim::{Event, GameStates, Simulation};
+
+use super::game::Game;
+use super::{draw_centered, text, App, Screen, Transition, YELLOW};
+
+/// How long a finished demo game stays on screen before the next one starts.
+const PAUSE_BETWEEN_GAMES: Duration = Duration::from_secs(3);
+
+/// Plays games between bots on the chosen level until any key is pressed.
+/// Every other game is a versus match.
+pub struct DemoScreen {
+    /// `None` if the game could not be set up, in which case the demo ends.
+    game: Option<Game>,
+    /// When the current game ended.
+    ended: Option<Instant>,
+    /// Whether the next game is a versus match.
+    versus: bool,
+}
+
+impl DemoScreen {
+    pub fn new(ctx: &mut Context, app: &App) -> Self {
+        let mut demo = DemoScreen {
+            game: None,
+            ended: None,
+            versus: false,
+        };
+        demo.next_game(ctx, app);
+        demo
+    }
+
+    fn next_game(&mut self, ctx: &mut Context, app: &App) {
+        let drivers: &[Driver] = if self.versus {
+            &[Driver::Bot(Bot::Bfs), Driver::Bot(Bot::Greedy)]
+        } else {
+            &[Driver::Bot(Bot::Bfs)]
+        };
+        let seed = Simulation::random_seed();
+        let sim = Simulation::with_rules(app.level.clone(), seed, drivers.len(), app.settings.rules());
+        self.game = match Game::new(ctx, app, sim, drivers, None) {
+            Ok(mut game) => {
+                game.stop_recording();
+                Some(game)
+            }
+            Err(e) => {
+                eprintln!("Could not start a demo: {}", e);
+                None
+            }
+        };
+        self.ended = None;
+        self.versus = !self.versus;
+    }
+}
+
+impl Screen for DemoScreen {
+    fn update(&mut self, ctx: &mut Context, app: &mut App) -> GameResult<Transition> {
+        let game = match &mut self.game {
+            Some(game) => game,
+            None => return Ok(Transition::Pop),
+        };
+        for event in game.step() {
+            if let Event::StateChanged {
+                to: GameStates::GameOver,
+                ..
+            }
+            | Event::StateChanged {
+                to: GameStates::Won,
+                ..
+            } = event
+            {
+                self.ended = Some(Instant::now());
+            }
+        }
+        if self.ended.is_some_and(|ended| ended.elapsed() >= PAUSE_BETWEEN_GAMES) {
+            self.next_game(ctx, app);
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        if let Some(game) = &mut self.game {
+            game.draw(ctx)?;
+        }
+        let y = 0.1 * app.screen_size.y;
+        draw_centered(ctx, app, &text("DEMO".to_string(), 80.0, YELLOW), y)?;
+        draw_centered(ctx, app, &text("Press any key".to_string(), 30.0, YELLOW), y + 90.0)
+    }
+
+    /// Any key ends the demo, whatever it is bound to.
+    fn key_down(&mut self, _ctx: &mut Context, _app: &mut App, _keycode: KeyCode) -> Transition {
+        Transition::Pop
+    }
+
+    fn action(&mut self, _ctx: &mut Context, _app: &mut App, _action: Action) -> Transition {
+        Transition::Pop
+    }
+
+    fn resize(&mut self, app: &App) {
+        if let Some(game) = &mut self.game {
+            game.resize(app.screen_size, app.cell_size);
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! The game itself: the board, the snake and the ticking simulation.
+
+use ggez::audio::SoundSource;
+use ggez::graphics::{self, Color};
+use ggez::{Context, GameResult};
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// The rules of the game live in the library, the code here only
+// translates input and draws.
+use snake_remix::bot::{Controller, Driver, View};
+use snake_remix::clock::{CatchUp, SystemClock, TickTimer};
+use snake_remix::controls::Action;
+use snake_remix::highscore;
+use snake_remix::level::Goal;
+use snake_remix::powerup::FoodKind;
+use snake_remix::replay::{Playback, Replay};
+use snake_remix::sim::{Ate, Event, GameStates, Input, Simulation, Snake};
+
+use super::overlay::{GameOverScreen, PauseScreen};
+use super::view::{draw_snake, GridView, Layout};
+use super::{App, Screen, Transition, Vector2};
+
+/// The colours of the snake sprites: the snake of a single player, then the
+/// players of a versus match.
+const SNAKE_COLORS: [&str; 3] = ["green", "blue", "yellow"];
+
+/// The names of the players of a versus match, after the colours of their snakes.
+pub const PLAYER_NAMES: [&str; 2] = ["Blue", "Yellow"];
+
+/// Food about to disappear blinks for this long before it does.
+const FOOD_BLINK: Duration = Duration::from_secs(2);
+
+/// The image a kind of food is drawn with.
+fn food_image_path(kind: FoodKind) -> String {
+    match kind {
+        FoodKind::Plain => "/png/element_red_square.png".to_string(),
+        kind => format!("/png/food_{}.png", kind),
+    }
+}
+
+/// The effects a snake is under and the seconds they have left, for the HUD.
+fn effects_text(snake: &Snake) -> String {
+    snake
+        .effects
+        .iter()
+        .map(|(effect, left)| format!(" {} {:.0}s", effect, left.as_secs_f32().ceil()))
+        .collect()
+}
+
+/// How soon a snake starves in survival, for the HUD.
+fn hunger_text(sim: &Simulation, snake: usize) -> String {
+    match sim.starves_in(snake) {
+        Some(left) => format!(" Starving in {:.0}s", left.as_secs_f32().ceil()),
+        None => String::new(),
+    }
+}
+
+/// How close the snake is to the goal of the level, for the HUD.
+fn goal_text(sim: &Simulation) -> String {
+    match sim.level.goal {
+        Some(Goal::Length(length)) => format!(" Goal: length {}/{}", sim.snake().length(), length),
+        Some(Goal::Points(points)) => format!(" Goal: {}/{} points", sim.snake().points, points),
+        None => String::new(),
+    }
+}
+
+/// The time left in a time attack, or else the time played, for the HUD.
+fn clock_text(sim: &Simulation) -> String {
+    let (label, time) = match sim.time_left() {
+        Some(left) => ("Time left", left),
+        None => ("Time", sim.game_time),
+    };
+    let seconds = time.as_secs();
+    format!("{}: {}:{:02}", label, seconds / 60, seconds % 60)
+}
+
+/// How long a snake that died flashes before it starts to crumble.
+const DEATH_FLASH: Duration = Duration::from_millis(600);
+/// How long the whole death animation lasts, flashing and crumbling.
+const DEATH_ANIMATION: Duration = Duration::from_millis(1400);
+
+/// How much of a snake of `length` segments is drawn `elapsed` after it died,
+/// and how opaque. First it flashes, then it crumbles away from the tail.
+fn death_frame(length: usize, elapsed: Duration) -> (usize, f32) {
+    if elapsed < DEATH_FLASH {
+        let on = (elapsed.as_millis() / 100).is_multiple_of(2);
+        (length, if on { 1.0 } else { 0.3 })
+    } else {
+        let crumbling = (elapsed - DEATH_FLASH).as_secs_f32() / (DEATH_ANIMATION - DEATH_FLASH).as_secs_f32();
+        let left = (1.0 - crumbling).max(0.0);
+        ((length as f32 * left).ceil() as usize, left)
+    }
+}
+
+/// A game being played or watched, and everything needed to draw it.
+pub struct Game {
+    /// The rules of the game: snakes, food and game state
+    pub sim: Simulation,
+
+    grid: GridView,
+    /// Where the grid is drawn and how large
+    layout: Layout,
+    /// Sprites used to draw the snakes, one batch per snake
+    snake_batches: Vec<graphics::spritebatch::SpriteBatch>,
+    /// Images used to draw the food, in the order of `FoodKind::ALL`
+    food_images: Vec<graphics::Image>,
+    /// And we turn elapsed time into whole ticks so that the game runs at the
+    /// same speed regardless of the frame rate.
+    timer: TickTimer<SystemClock>,
+
+    /// Where finished games are saved, if they are recorded.
+    record_dir: Option<PathBuf>,
+    /// The game being recorded right now.
+    recording: Option<Replay>,
+    /// Turns fed to the simulation when watching a replay.
+    playback: Option<Playback>,
+    /// The bots steering the snakes that are not steered by a person.
+    controllers: Vec<Option<Box<dyn Controller>>>,
+    /// When the snakes that died in this game did, for the death animation.
+    died_at: Option<Instant>,
+}
+
+impl Game {
+    pub fn new(
+        ctx: &mut Context,
+        app: &App,
+        sim: Simulation,
+        drivers: &[Driver],
+        playback: Option<Playback>,
+    ) -> GameResult<Game> {
+        // A snake playing alone is green, in a versus match the first player is
+        // blue and the second one yellow.
+        let colors: &[&str] = if sim.is_versus() { &SNAKE_COLORS[1..] } else { &SNAKE_COLORS[..1] };
+        let snake_batches = colors
+            .iter()
+            .map(|color| {
+                let image = graphics::Image::new(ctx, format!("/png/element_{}_square.png", color))?;
+                Ok(graphics::spritebatch::SpriteBatch::new(image))
+            })
+            .collect::<GameResult<_>>()?;
+
+        let mut timer = TickTimer::new(SystemClock::new(), CatchUp::default());
+        timer.set_time_scale(app.speed);
+
+        Ok(Game {
+            grid: GridView::new(ctx, &sim.level),
+            layout: Layout::new(app.screen_size, &sim.level.grid, app.cell_size),
+            snake_batches,
+            food_images: FoodKind::ALL
+                .iter()
+                .map(|kind| graphics::Image::new(ctx, food_image_path(*kind)))
+                .collect::<GameResult<_>>()?,
+            timer,
+            recording: app.record_dir.as_ref().map(|_| Replay::for_simulation(&sim)),
+            record_dir: app.record_dir.clone(),
+            playback,
+            controllers: drivers
+                .iter()
+                .enumerate()
+                .map(|(i, driver)| driver.controller(sim.seed.wrapping_add(i as u64)))
+                .collect(),
+            died_at: None,
+            sim,
+        })
+    }
+
+    /// The high-score table this game counts towards.
+    pub fn category(&self) -> String {
+        highscore::category(&self.sim.level, &self.sim.rules)
+    }
+
+    /// Whether a replay is steering instead of the player.
+    pub fn is_watching(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// The snake a player steers. When only one snake is steered by a person,
+    /// both players' keys steer that one.
+    pub fn human_snake(&self, player: usize) -> Option<usize> {
+        let humans: Vec<usize> = (0..self.sim.snakes.len())
+            .filter(|i| self.controllers.get(*i).is_none_or(|c| c.is_none()))
+            .collect();
+        match humans.len() {
+            0 | 1 => humans.first().cloned(),
+            _ => humans.get(player).cloned(),
+        }
+    }
+
+    /// Whether the death animation of a snake that just died is still playing.
+    pub fn is_dying(&self) -> bool {
+        self.died_at.is_some_and(|died_at| died_at.elapsed() < DEATH_ANIMATION)
+    }
+
+    /// Advances the simulation once for every whole tick that is due and returns
+    /// what happened.
+    pub fn step(&mut self) -> Vec<Event> {
+        // First we let the timer measure how much time has passed, then we advance
+        // the simulation once for every whole tick that is due. If the game is over
+        // or paused, the simulation will just do nothing.
+        let mut all_events = Vec::new();
+        self.timer.update();
+        while self.timer.tick(self.sim.tick_interval()) {
+            let tick = self.sim.tick;
+            let inputs = match &mut self.playback {
+                Some(playback) => playback.inputs(tick),
+                None => {
+                    let sim = &self.sim;
+                    self.controllers
+                        .iter_mut()
+                        .enumerate()
+                        .filter_map(|(snake, controller)| {
+                            let dir = controller.as_mut()?.next_direction(&View::new(sim, snake))?;
+                            Some(Input::Turn { snake, dir })
+                        })
+                        .collect()
+                }
+            };
+            let events = self.sim.step(&inputs);
+            self.handle_events(tick, &events);
+            all_events.extend(events);
+        }
+        all_events
+    }
+
+    /// Hands an input to the simulation right away.
+    pub fn apply(&mut self, input: Input) -> Option<Event> {
+        let tick = self.sim.tick;
+        let event = self.sim.apply(input)?;
+        self.handle_events(tick, &[event]);
+        Some(event)
+    }
+
+    /// Starts measuring time again after the game was not updated for a while,
+    /// e.g. because it was paused, so that it does not try to catch up.
+    pub fn resume(&mut self) {
+        self.timer.reset();
+    }
+
+    pub fn resize(&mut self, screen: Vector2, cell_size: Option<f32>) {
+        self.layout = Layout::new(screen, &self.sim.level.grid, cell_size);
+    }
+
+    /// Keeps the recording up to date. `tick` is the tick the events happened on.
+    fn handle_events(&mut self, tick: u64, events: &[Event]) {
+        for event in events {
+            if let Some(recording) = &mut self.recording {
+                recording.record(tick, event);
+            }
+            match event {
+                Event::StateChanged {
+                    to: GameStates::GameOver,
+                    ..
+                }
+                | Event::StateChanged {
+                    to: GameStates::Won,
+                    ..
+                } => self.save_recording(),
+                Event::Died { .. } => {
+                    self.died_at.get_or_insert_with(Instant::now);
+                }
+                Event::StateChanged {
+                    from: GameStates::Restart,
+                    ..
+                } => {
+                    // A new game has started, the replay (if any) is over.
+                    self.playback = None;
+                    self.died_at = None;
+                    if self.recording.is_some() {
+                        self.recording = Some(Replay::for_simulation(&self.sim));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Neither records this game nor the ones after it.
+    pub fn stop_recording(&mut self) {
+        self.recording = None;
+        self.record_dir = None;
+    }
+
+    /// Writes the current recording to `<record_dir>/<seed>.replay`.
+    pub fn save_recording(&mut self) {
+        if let (Some(recording), Some(dir)) = (&mut self.recording, &self.record_dir) {
+            recording.finish(&self.sim);
+            let path = dir.join(format!("{}.replay", recording.seed));
+            if let Err(e) = recording.save(&path) {
+                eprintln!("Could not save replay to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Draws the board with the snakes and the food on it.
+    pub fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        // Draw grid.
+        self.grid.draw(ctx, &self.layout)?;
+
+        // Then we draw the snakes and the food. Snakes that died are animated.
+        for (snake, batch) in self.sim.snakes.iter().zip(self.snake_batches.iter_mut()) {
+            let (shown, alpha) = match (snake.died, self.died_at) {
+                (Some(_), Some(died_at)) => death_frame(snake.length(), died_at.elapsed()),
+                _ => (snake.length(), 1.0),
+            };
+            draw_snake(ctx, snake, batch, &self.layout, shown, Color::new(1.0, 1.0, 1.0, alpha))?;
+        }
+        // Food that is about to disappear blinks.
+        for food in &self.sim.foods {
+            let blinking = food
+                .left
+                .is_some_and(|left| left < FOOD_BLINK && (left.as_millis() / 150).is_multiple_of(2));
+            if !blinking {
+                let kind = FoodKind::ALL.iter().position(|kind| *kind == food.kind).unwrap_or(0);
+                graphics::draw(ctx, &self.food_images[kind], self.layout.cell_param(food.pos))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The screen the game is played on. The game itself is kept in `App::game`, so
+/// that the overlays on top of this screen can get at it too.
+pub struct GameScreen {
+    text: graphics::Text,
+    /// The game ended; the game over screen is shown once the death animation
+    /// has finished.
+    ended: bool,
+}
+
+impl GameScreen {
+    pub fn new() -> Self {
+        GameScreen {
+            text: graphics::Text::new("Hello world!"),
+            ended: false,
+        }
+    }
+}
+
+impl Screen for GameScreen {
+    /// Update will happen on every frame before it is drawn. This is where we update
+    /// our game state to react to whatever is happening in the game world.
+    fn update(&mut self, ctx: &mut Context, app: &mut App) -> GameResult<Transition> {
+        let game = match &mut app.game {
+            Some(game) => game,
+            None => return Ok(Transition::Pop),
+        };
+
+        self.text = graphics::Text::new(if game.sim.is_versus() {
+            let scoreboard = &game.sim.scoreboard;
+            format!(
+                "FPS: {:.0} Round: {} {}: {} ({} wins){}{} {}: {} ({} wins){}{} {} Seed: {}",
+                ggez::timer::fps(ctx),
+                scoreboard.rounds() + 1,
+                PLAYER_NAMES[0],
+                game.sim.snakes[0].points,
+                scoreboard.wins[0],
+                effects_text(&game.sim.snakes[0]),
+                hunger_text(&game.sim, 0),
+                PLAYER_NAMES[1],
+                game.sim.snakes[1].points,
+                scoreboard.wins[1],
+                effects_text(&game.sim.snakes[1]),
+                hunger_text(&game.sim, 1),
+                clock_text(&game.sim),
+                game.sim.seed,
+            )
+        } else {
+            format!(
+                "FPS: {:.0} Points: {} Best: {}{}{}{} {} Seed: {}",
+                ggez::timer::fps(ctx),
+                game.sim.snake().points,
+                app.high_scores.best(&game.category()).unwrap_or(0),
+                goal_text(&game.sim),
+                effects_text(game.sim.snake()),
+                hunger_text(&game.sim, 0),
+                clock_text(&game.sim),
+                game.sim.seed,
+            )
+        });
+
+        let mut died = false;
+        for event in game.step() {
+            match event {
+                Event::Ate { ate: Ate::Food, .. } => {
+                    let _ = app.hit_sound.play();
+                }
+                Event::Died { .. } => died = true,
+                Event::StateChanged {
+                    to: GameStates::GameOver,
+                    ..
+                }
+                | Event::StateChanged {
+                    to: GameStates::Won,
+                    ..
+                } => self.ended = true,
+                _ => {}
+            }
+        }
+        // When both snakes of a versus match die at once, one sound will do.
+        if died {
+            let _ = app.death_sound.play();
+        }
+
+        // Finally we return `Ok` to indicate we didn't run into any errors
+        if self.ended && !game.is_dying() {
+            self.ended = false;
+            return Ok(Transition::Push(Box::new(GameOverScreen::new(app))));
+        }
+        Ok(Transition::None)
+    }
+
+    /// draw is where we should actually render the game's current state.
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        if let Some(game) = &mut app.game {
+            game.draw(ctx)?;
+        }
+
+        let dest_point = mint::Vector2 { x: (0.0), y: (0.0) };
+        graphics::draw(ctx, &self.text, (dest_point,))
+    }
+
+    fn action(&mut self, _ctx: &mut Context, app: &mut App, action: Action) -> Transition {
+        let game = match &mut app.game {
+            Some(game) => game,
+            None => return Transition::Pop,
+        };
+
+        // Here we attempt to convert the action into a Direction, and hand it to
+        // the simulation which decides whether the snake may turn that way. With one
+        // person playing, both players' keys steer their snake. While watching a
+        // replay the replay steers.
+        if let Some(dir) = action.direction() {
+            if let (Some(snake), false) = (game.human_snake(action.player()), game.is_watching()) {
+                game.apply(Input::Turn { snake, dir });
+            }
+            return Transition::None;
+        }
+        match action {
+            Action::Quit => Transition::Quit,
+            Action::Pause | Action::Cancel => match game.apply(Input::Pause) {
+                Some(_) => Transition::Push(Box::new(PauseScreen::new())),
+                None => Transition::None,
+            },
+            Action::ToggleMusic => {
+                app.toggle_music();
+                Transition::None
+            }
+            _ => Transition::None,
+        }
+    }
+}
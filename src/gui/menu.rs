@@ -0,0 +1,224 @@
+//! Menus: the title screen and choosing what to play.
+
+use ggez::{Context, GameResult};
+
+use snake_remix::highscore;
+use snake_remix::level::Level;
+use snake_remix::bot::Driver;
+use snake_remix::controls::Action;
+use snake_remix::sim::{Boundary, Direction, Grid};
+
+use super::campaign::CampaignScreen;
+use super::game::GameScreen;
+use super::options::OptionsScreen;
+use super::scores::HighScoresScreen;
+use super::{draw_centered, label, text, App, Screen, Transition, WHITE, YELLOW};
+
+/// What the player did in a menu.
+pub enum Choice {
+    None,
+    /// The item was picked.
+    Chosen(usize),
+    /// The value of the item should go down.
+    Left(usize),
+    /// The value of the item should go up.
+    Right(usize),
+    /// The player wants out of the menu.
+    Back,
+    /// The player wants out of the game.
+    Quit,
+}
+
+/// A list of items, one of which is selected.
+pub struct Menu {
+    pub items: Vec<String>,
+    pub selected: usize,
+}
+
+impl Menu {
+    pub fn new(items: Vec<String>) -> Self {
+        Menu { items, selected: 0 }
+    }
+
+    /// Moves the selection up and down and reports everything else. Both
+    /// players' keys work in menus.
+    pub fn action(&mut self, action: Action) -> Choice {
+        let count = self.items.len();
+        match action.direction() {
+            Some(Direction::Up) => {
+                self.selected = (self.selected + count - 1) % count;
+                return Choice::None;
+            }
+            Some(Direction::Down) => {
+                self.selected = (self.selected + 1) % count;
+                return Choice::None;
+            }
+            Some(Direction::Left) => return Choice::Left(self.selected),
+            Some(Direction::Right) => return Choice::Right(self.selected),
+            None => {}
+        }
+        match action {
+            Action::Confirm => Choice::Chosen(self.selected),
+            Action::Cancel => Choice::Back,
+            Action::Quit => Choice::Quit,
+            _ => Choice::None,
+        }
+    }
+
+    /// Draws the items one below the other, starting at `y`.
+    pub fn draw(&self, ctx: &mut Context, app: &App, mut y: f32) -> GameResult {
+        for (i, item) in self.items.iter().enumerate() {
+            let item = if i == self.selected {
+                text(format!("> {} <", item), 40.0, YELLOW)
+            } else {
+                text(item.clone(), 40.0, WHITE)
+            };
+            draw_centered(ctx, app, &item, y)?;
+            y += 55.0;
+        }
+        Ok(())
+    }
+}
+
+const PLAY: usize = 0;
+const CAMPAIGN: usize = 1;
+const VERSUS: usize = 2;
+const VERSUS_COMPUTER: usize = 3;
+const MODE_SELECT: usize = 4;
+const BOARD_SELECT: usize = 5;
+const OPTIONS: usize = 6;
+const HIGH_SCORES: usize = 7;
+const QUIT: usize = 8;
+
+/// The first screen, and where every game returns to.
+pub struct TitleScreen {
+    menu: Menu,
+}
+
+impl TitleScreen {
+    pub fn new(app: &App) -> Self {
+        TitleScreen {
+            menu: Menu::new(TitleScreen::items(app)),
+        }
+    }
+
+    /// The items of the menu; the mode is changed right in it with left and right.
+    fn items(app: &App) -> Vec<String> {
+        vec![
+            "Play".to_string(),
+            "Campaign".to_string(),
+            "Versus".to_string(),
+            "Versus computer".to_string(),
+            format!("Mode select: {}", app.settings.mode.name()),
+            "Board select".to_string(),
+            "Options".to_string(),
+            "High scores".to_string(),
+            "Quit".to_string(),
+        ]
+    }
+}
+
+impl Screen for TitleScreen {
+    fn shows_demo(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        self.menu.items = TitleScreen::items(app);
+        let y = 0.2 * app.screen_size.y;
+        draw_centered(ctx, app, &text("SNAKE REMIX".to_string(), 120.0, YELLOW), y)?;
+        let mode = label(format!("Playing: {}", highscore::category(&app.level, &app.settings.rules())), 24.0);
+        draw_centered(ctx, app, &mode, y + 140.0)?;
+        self.menu.draw(ctx, app, 0.45 * app.screen_size.y)
+    }
+
+    fn action(&mut self, ctx: &mut Context, app: &mut App, action: Action) -> Transition {
+        let drivers = match self.menu.action(action) {
+            Choice::Chosen(PLAY) => vec![Driver::Human],
+            Choice::Chosen(VERSUS) => vec![Driver::Human, Driver::Human],
+            Choice::Chosen(VERSUS_COMPUTER) => vec![Driver::Human, Driver::Bot(app.settings.bot)],
+            Choice::Chosen(CAMPAIGN) => return Transition::Push(Box::new(CampaignScreen::new(app))),
+            Choice::Chosen(MODE_SELECT) | Choice::Right(MODE_SELECT) => {
+                app.settings.mode = app.settings.mode.cycle(true);
+                app.save_settings();
+                return Transition::None;
+            }
+            Choice::Left(MODE_SELECT) => {
+                app.settings.mode = app.settings.mode.cycle(false);
+                app.save_settings();
+                return Transition::None;
+            }
+            Choice::Chosen(BOARD_SELECT) => return Transition::Push(Box::new(BoardSelectScreen::new(app))),
+            Choice::Chosen(OPTIONS) => return Transition::Push(Box::new(OptionsScreen::new(app))),
+            Choice::Chosen(HIGH_SCORES) => return Transition::Push(Box::new(HighScoresScreen::new(app, None, None))),
+            Choice::Chosen(QUIT) | Choice::Back | Choice::Quit => return Transition::Quit,
+            _ => return Transition::None,
+        };
+        match app.new_game(ctx, &drivers) {
+            Ok(()) => Transition::Push(Box::new(GameScreen::new())),
+            Err(e) => {
+                eprintln!("Could not start a game: {}", e);
+                Transition::None
+            }
+        }
+    }
+}
+
+/// Chooses the board the next games are played on: an open board, with or
+/// without walls around it, or one of the levels in `resources/levels`.
+pub struct BoardSelectScreen {
+    menu: Menu,
+    levels: Vec<String>,
+}
+
+impl BoardSelectScreen {
+    pub fn new(app: &App) -> Self {
+        let levels = Level::names();
+        let mut items = vec!["Open board".to_string(), "Open board with walls".to_string()];
+        items.extend(levels.iter().cloned());
+
+        let mut menu = Menu::new(items);
+        menu.selected = if app.level.is_open() {
+            match app.level.grid.boundary {
+                Boundary::Wrap => 0,
+                Boundary::Solid => 1,
+            }
+        } else {
+            levels.iter().position(|name| *name == app.level.name).map_or(0, |i| i + 2)
+        };
+        BoardSelectScreen { menu, levels }
+    }
+}
+
+impl Screen for BoardSelectScreen {
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        let y = 0.15 * app.screen_size.y;
+        draw_centered(ctx, app, &label("BOARD SELECT".to_string(), 60.0), y)?;
+        self.menu.draw(ctx, app, y + 100.0)
+    }
+
+    fn action(&mut self, _ctx: &mut Context, app: &mut App, action: Action) -> Transition {
+        let open = |boundary| {
+            Level::open(Grid {
+                boundary,
+                ..app.settings.grid()
+            })
+        };
+        let level = match self.menu.action(action) {
+            Choice::Chosen(0) => open(Boundary::Wrap),
+            Choice::Chosen(1) => open(Boundary::Solid),
+            Choice::Chosen(i) => match Level::find(&self.levels[i - 2]) {
+                Ok(level) => level,
+                Err(e) => {
+                    eprintln!("Could not load level {}: {}", self.levels[i - 2], e);
+                    return Transition::None;
+                }
+            },
+            Choice::Back => return Transition::Pop,
+            Choice::Quit => return Transition::Quit,
+            _ => return Transition::None,
+        };
+        app.level = level;
+        Transition::Pop
+    }
+}
@@ -0,0 +1,530 @@
+//! The screens of the game and the stack they are kept on.
+//!
+//! Every screen (the title menu, the game itself, the pause and game over
+//! overlays, ...) implements `Screen`. Screens are kept on a stack: only the top
+//! one gets input and updates, and it tells the stack with a `Transition` whether
+//! to open another screen on top of it, to close it and so on. Overlays like the
+//! pause screen are drawn on top of the screens below them.
+
+pub mod campaign;
+pub mod controls;
+pub mod demo;
+pub mod game;
+pub mod menu;
+pub mod options;
+pub mod overlay;
+pub mod scores;
+pub mod view;
+
+use ggez::audio;
+use ggez::audio::SoundSource;
+use ggez::event;
+use ggez::event::{Axis, Button, GamepadId, KeyCode, KeyMods};
+use ggez::graphics::{self, Color, Scale, TextFragment};
+use ggez::{nalgebra as na, Context, GameResult};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use snake_remix::bot::Driver;
+use snake_remix::campaign::{Campaign, Progress};
+use snake_remix::controls::{Action, Stick};
+use snake_remix::highscore::HighScores;
+use snake_remix::level::Level;
+use snake_remix::replay::{Playback, Replay};
+use snake_remix::settings::{DisplayMode, Settings};
+use snake_remix::sim::{Direction, GameStates, Rules, Simulation};
+
+use self::demo::DemoScreen;
+use self::game::Game;
+
+pub type Vector2 = na::Vector2<f32>;
+
+pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+pub const YELLOW: Color = Color::new(1.0, 1.0, 0.0, 1.0);
+pub const GREY: Color = Color::new(0.7, 0.7, 0.7, 1.0);
+
+/// The ggez window mode for a display mode, at `size` pixels where that applies.
+pub fn window_mode(display: DisplayMode, size: Vector2) -> ggez::conf::WindowMode {
+    let mode = ggez::conf::WindowMode::default().dimensions(size.x, size.y);
+    match display {
+        DisplayMode::Windowed => mode.resizable(true),
+        DisplayMode::Borderless => mode.fullscreen_type(ggez::conf::FullscreenType::Desktop),
+        DisplayMode::Fullscreen => mode.fullscreen_type(ggez::conf::FullscreenType::True),
+    }
+}
+
+/// Text of the given size and colour.
+pub fn text(text: String, scale: f32, color: Color) -> graphics::Text {
+    graphics::Text::new(TextFragment {
+        text,
+        color: Some(color),
+        font: Some(graphics::Font::default()),
+        scale: Some(Scale::uniform(scale)),
+    })
+}
+
+/// White text of the given size.
+pub fn label(text: String, scale: f32) -> graphics::Text {
+    self::text(text, scale, WHITE)
+}
+
+/// Draws `text` centred horizontally on the screen with its top at `y`.
+pub fn draw_centered(ctx: &mut Context, app: &App, text: &graphics::Text, y: f32) -> GameResult {
+    let dest_point = mint::Vector2 {
+        x: 0.5 * app.screen_size.x - 0.5 * text.width(ctx) as f32,
+        y,
+    };
+    graphics::draw(ctx, text, (dest_point,))
+}
+
+/// Darkens the whole screen, so that an overlay is readable on top of the board.
+pub fn draw_shade(ctx: &mut Context, app: &App) -> GameResult {
+    let rect = graphics::Rect::new(0.0, 0.0, app.screen_size.x, app.screen_size.y);
+    let shade = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::fill(),
+        rect,
+        Color::new(0.0, 0.0, 0.0, 0.6),
+    )?;
+    graphics::draw(ctx, &shade, graphics::DrawParam::default())
+}
+
+/// We also create a helper function that will let us convert between a
+/// `ggez` `Keycode` and the `Action` it is bound to. Of course, not every
+/// key is bound to something, so we return `None` if this is the case.
+pub fn action(app: &App, key: KeyCode) -> Option<Action> {
+    app.settings.keys.action(&key_name(key))
+}
+
+/// Keys are named in the settings the way ggez names them, e.g. `Up`, `W` or `Escape`.
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+/// The first key bound to `action`, to tell the player what to press.
+pub fn key_hint(app: &App, action: Action) -> &str {
+    app.settings.keys.keys(action).first().map_or("?", |key| key.as_str())
+}
+
+/// What should happen to the stack after a screen handled something.
+pub enum Transition {
+    /// Stay on this screen.
+    None,
+    /// Open another screen on top of this one.
+    Push(Box<dyn Screen>),
+    /// Close this screen and go back to the one below.
+    Pop,
+    /// Close this screen and open another one in its place.
+    Replace(Box<dyn Screen>),
+    /// Close every screen but the title screen at the bottom.
+    ToTitle,
+    /// Quit the game.
+    Quit,
+}
+
+/// One screen of the game.
+pub trait Screen {
+    /// Called every frame while this screen is on top.
+    fn update(&mut self, _ctx: &mut Context, _app: &mut App) -> GameResult<Transition> {
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult;
+
+    /// A key was pressed while this screen is on top. Unless a screen wants the
+    /// keys themselves, they are turned into the actions they are bound to.
+    fn key_down(&mut self, ctx: &mut Context, app: &mut App, keycode: KeyCode) -> Transition {
+        match action(app, keycode) {
+            Some(action) => self.action(ctx, app, action),
+            None => Transition::None,
+        }
+    }
+
+    /// The player did something, with a key or a gamepad.
+    fn action(&mut self, ctx: &mut Context, app: &mut App, action: Action) -> Transition;
+
+    /// A character was typed while this screen is on top.
+    fn text_input(&mut self, _app: &mut App, _character: char) -> Transition {
+        Transition::None
+    }
+
+    /// The window changed size.
+    fn resize(&mut self, _app: &App) {}
+
+    /// Whether the demo starts when nobody touches anything for a while on
+    /// this screen.
+    fn shows_demo(&self) -> bool {
+        false
+    }
+
+    /// Whether the screens below this one are drawn too.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// Everything the screens share: preferences, sounds, high scores and the game
+/// being played, if any.
+pub struct App {
+    pub hit_sound: audio::Source,
+    pub death_sound: audio::Source,
+    pub music: audio::Source,
+
+    /// Preferences as saved, without the command line options applied
+    pub settings: Settings,
+    /// Where the settings are saved to when they change
+    pub settings_path: Option<PathBuf>,
+
+    /// The best scores so far, by board
+    pub high_scores: HighScores,
+    /// Where the high scores are saved to
+    pub high_scores_path: Option<PathBuf>,
+    /// The name last entered for a high score, offered again next time
+    pub player_name: String,
+
+    /// The stages of the campaign
+    pub campaign: Campaign,
+    /// How far the player got in the campaign
+    pub progress: Progress,
+    /// Where the progress is saved to
+    pub progress_path: Option<PathBuf>,
+    /// The stage of the campaign being played, if the game is one
+    pub stage: Option<usize>,
+
+    /// Size of the window in pixels
+    pub screen_size: Vector2,
+    /// Cell size asked for on the command line, kept for when the window is resized
+    pub cell_size: Option<f32>,
+    /// How fast games run compared to normal speed
+    pub speed: f64,
+    /// The level the next game is played on
+    pub level: Level,
+    /// Seed for the next game, if one was asked for
+    pub seed: Option<u64>,
+    /// Where finished games are saved, if they are recorded
+    pub record_dir: Option<PathBuf>,
+
+    /// The game being played, or watched, right now
+    pub game: Option<Game>,
+}
+
+impl App {
+    pub fn new(ctx: &mut Context, settings: Settings, settings_path: Option<PathBuf>) -> GameResult<App> {
+        // The ttf file will be in your resources directory. Later, we
+        // will mount that directory so we can omit it in the path here.
+        let _font = graphics::Font::new(ctx, "/DejaVuSerif.ttf");
+
+        let mut hit_sound = audio::Source::new(ctx, "/phaseJump5.mp3")?;
+
+        hit_sound.set_volume(settings.sound_volume);
+
+        let mut death_sound = audio::Source::new(ctx, "/death.wav")?;
+
+        death_sound.set_volume(settings.sound_volume);
+
+        let mut music = audio::Source::new(ctx, "/BoxCat_Games_-_10_-_Epic_Song.mp3")?;
+
+        music.set_volume(settings.music_volume);
+        music.set_repeat(true);
+
+        let _ = music.play();
+        if !settings.music_on {
+            music.pause();
+        }
+
+        // Use the real size of the window for drawing, whatever size was asked for.
+        let (width, height) = graphics::drawable_size(ctx);
+        graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, width, height))?;
+
+        let high_scores_path = HighScores::path();
+        let progress_path = Progress::path();
+
+        Ok(App {
+            hit_sound,
+            death_sound,
+            music,
+            speed: settings.speed,
+            level: Level::open(settings.grid()),
+            settings,
+            settings_path,
+            high_scores: HighScores::load_or_default(high_scores_path.as_deref()),
+            high_scores_path,
+            player_name: String::new(),
+            campaign: Campaign::load_or_default(),
+            progress: Progress::load_or_default(progress_path.as_deref()),
+            progress_path,
+            stage: None,
+            screen_size: Vector2::new(width, height),
+            cell_size: None,
+            seed: None,
+            record_dir: None,
+            game: None,
+        })
+    }
+
+    /// Starts a new game on the chosen level with a snake for every driver: one
+    /// for a game alone, two for a versus match.
+    pub fn new_game(&mut self, ctx: &mut Context, drivers: &[Driver]) -> GameResult {
+        let seed = self.seed.take().unwrap_or_else(Simulation::random_seed);
+        let sim = Simulation::with_rules(self.level.clone(), seed, drivers.len(), self.settings.rules());
+        self.game = Some(Game::new(ctx, self, sim, drivers, None)?);
+        self.stage = None;
+        Ok(())
+    }
+
+    /// Starts a stage of the campaign. Stages are played by the classic rules,
+    /// at the chosen difficulty.
+    pub fn play_stage(&mut self, ctx: &mut Context, stage: usize) -> GameResult {
+        let level = self.campaign.stages[stage].clone();
+        let rules = Rules {
+            speed: self.settings.rules().speed,
+            ..Rules::default()
+        };
+        let seed = self.seed.take().unwrap_or_else(Simulation::random_seed);
+        let sim = Simulation::with_rules(level, seed, 1, rules);
+        self.game = Some(Game::new(ctx, self, sim, &[Driver::Human], None)?);
+        self.stage = Some(stage);
+        Ok(())
+    }
+
+    /// Starts watching a replay.
+    pub fn watch(&mut self, ctx: &mut Context, replay: &Replay) -> GameResult {
+        let sim = replay.simulation();
+        let drivers = vec![Driver::Human; sim.snakes.len()];
+        self.game = Some(Game::new(ctx, self, sim, &drivers, Some(Playback::new(replay)))?);
+        self.stage = None;
+        Ok(())
+    }
+
+    pub fn toggle_music(&mut self) {
+        self.settings.music_on = !self.settings.music_on;
+
+        if self.settings.music_on {
+            self.music.resume();
+        } else {
+            self.music.pause();
+        }
+        self.save_settings();
+    }
+
+    /// Keeps changed preferences for the next launch.
+    pub fn save_settings(&self) {
+        if let Some(path) = &self.settings_path {
+            if let Err(e) = self.settings.save(path) {
+                eprintln!("Could not save settings to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    pub fn save_high_scores(&self) {
+        if let Some(path) = &self.high_scores_path {
+            if let Err(e) = self.high_scores.save(path) {
+                eprintln!("Could not save high scores to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    pub fn save_progress(&self) {
+        if let Some(path) = &self.progress_path {
+            if let Err(e) = self.progress.save(path) {
+                eprintln!("Could not save campaign progress to {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// The stack of screens. This implements ggez's `EventHandler` trait and passes
+/// everything that happens on to the screen on top.
+pub struct Stack {
+    app: App,
+    screens: Vec<Box<dyn Screen>>,
+    /// Every gamepad that was used, in the order they were first used. The
+    /// first one steers the first player's snake, the second one the other.
+    gamepads: Vec<GamepadId>,
+    /// The analog sticks of those gamepads
+    sticks: HashMap<GamepadId, Stick>,
+    /// When a key or button was last pressed
+    last_input: Instant,
+}
+
+impl Stack {
+    pub fn new(app: App, screens: Vec<Box<dyn Screen>>) -> Self {
+        Stack {
+            app,
+            screens,
+            gamepads: Vec::new(),
+            sticks: HashMap::new(),
+            last_input: Instant::now(),
+        }
+    }
+
+    fn apply(&mut self, ctx: &mut Context, transition: Transition) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(screen) => self.screens.push(screen),
+            Transition::Pop => {
+                self.screens.pop();
+            }
+            Transition::Replace(screen) => {
+                self.screens.pop();
+                self.screens.push(screen);
+            }
+            Transition::ToTitle => {
+                self.quit_game();
+                self.screens.truncate(1);
+            }
+            Transition::Quit => ggez::event::quit(ctx),
+        }
+        if self.screens.is_empty() {
+            ggez::event::quit(ctx);
+        }
+    }
+
+    /// Hands an action to the screen on top.
+    fn action(&mut self, ctx: &mut Context, action: Action) {
+        let transition = match self.screens.last_mut() {
+            Some(screen) => screen.action(ctx, &mut self.app, action),
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+    }
+
+    /// The player a gamepad belongs to.
+    fn player(&mut self, id: GamepadId) -> usize {
+        match self.gamepads.iter().position(|known| *known == id) {
+            Some(i) => i.min(1),
+            None => {
+                self.gamepads.push(id);
+                (self.gamepads.len() - 1).min(1)
+            }
+        }
+    }
+
+    /// Saves the game in progress, if it is being recorded, and forgets it.
+    fn quit_game(&mut self) {
+        if let Some(mut game) = self.app.game.take() {
+            if let GameStates::GameOn | GameStates::Pause = game.sim.game_states {
+                game.save_recording();
+            }
+        }
+    }
+}
+
+impl event::EventHandler for Stack {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        let transition = match self.screens.last_mut() {
+            Some(screen) => screen.update(ctx, &mut self.app)?,
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+
+        // Nobody has touched anything for a while, so the bots show how it is done.
+        let demo_after = self.app.settings.demo_after;
+        let idle = self.last_input.elapsed() >= Duration::from_secs(demo_after.into());
+        if demo_after > 0 && idle && self.screens.last().is_some_and(|screen| screen.shows_demo()) {
+            let demo = DemoScreen::new(ctx, &self.app);
+            self.apply(ctx, Transition::Push(Box::new(demo)));
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        // First we clear the screen to a nice (well, maybe pretty glaring ;)) green
+        graphics::clear(ctx, [0.2, 0.3, 0.6, 1.0].into());
+
+        // Overlays are drawn on top of whatever is below them, down to the
+        // first screen that covers everything.
+        let first = self
+            .screens
+            .iter()
+            .rposition(|screen| !screen.is_overlay())
+            .unwrap_or(0);
+        for screen in self.screens[first..].iter_mut() {
+            screen.draw(ctx, &mut self.app)?;
+        }
+
+        // Finally we call graphics::present to cycle the gpu's framebuffer and display
+        // the new frame we just drew.
+        graphics::present(ctx)?;
+        // We yield the current thread until the next update
+        ggez::timer::yield_now();
+
+        // And return success.
+        Ok(())
+    }
+
+    /// key_down_event gets fired when a key gets pressed.
+    fn key_down_event(&mut self, ctx: &mut Context, keycode: KeyCode, _keymod: KeyMods, _repeat: bool) {
+        self.last_input = Instant::now();
+        let transition = match self.screens.last_mut() {
+            Some(screen) => screen.key_down(ctx, &mut self.app, keycode),
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+    }
+
+    /// Gamepads are not rebindable: the D-pad steers, Start pauses, the bottom
+    /// face button confirms and the right one cancels.
+    fn gamepad_button_down_event(&mut self, ctx: &mut Context, button: Button, id: GamepadId) {
+        self.last_input = Instant::now();
+        let player = self.player(id);
+        let action = match button {
+            Button::DPadUp => Action::turn(player, Direction::Up),
+            Button::DPadDown => Action::turn(player, Direction::Down),
+            Button::DPadLeft => Action::turn(player, Direction::Left),
+            Button::DPadRight => Action::turn(player, Direction::Right),
+            Button::Start => Action::Pause,
+            Button::Select => Action::ToggleMusic,
+            Button::South => Action::Confirm,
+            Button::East => Action::Cancel,
+            _ => return,
+        };
+        self.action(ctx, action);
+    }
+
+    /// The left stick steers like the D-pad once it is pushed out of the deadzone.
+    /// Some gamepads report their D-pad as axes too.
+    fn gamepad_axis_event(&mut self, ctx: &mut Context, axis: Axis, value: f32, id: GamepadId) {
+        let player = self.player(id);
+        let deadzone = self.app.settings.gamepad_deadzone;
+        let stick = self.sticks.entry(id).or_insert_with(|| Stick::new(deadzone));
+        let direction = match axis {
+            Axis::LeftStickX | Axis::DPadX => stick.move_x(value),
+            Axis::LeftStickY | Axis::DPadY => stick.move_y(value),
+            _ => None,
+        };
+        if let Some(direction) = direction {
+            self.last_input = Instant::now();
+            self.action(ctx, Action::turn(player, direction));
+        }
+    }
+
+    fn text_input_event(&mut self, ctx: &mut Context, character: char) {
+        let transition = match self.screens.last_mut() {
+            Some(screen) => screen.text_input(&mut self.app, character),
+            None => Transition::None,
+        };
+        self.apply(ctx, transition);
+    }
+
+    /// The window changed size: draw at the new size and fit the grid into it again,
+    /// so that nothing ends up off-screen.
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        let _ = graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, width, height));
+        self.app.screen_size = Vector2::new(width, height);
+        if let Some(game) = &mut self.app.game {
+            game.resize(self.app.screen_size, self.app.cell_size);
+        }
+        for screen in self.screens.iter_mut() {
+            screen.resize(&self.app);
+        }
+    }
+
+    /// Save the game in progress when the window is closed.
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        self.quit_game();
+        false
+    }
+}
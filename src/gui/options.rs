@@ -0,0 +1,173 @@
+//! The options screen, where the settings are changed.
+
+use ggez::audio::SoundSource;
+use ggez::graphics;
+use ggez::{Context, GameResult};
+
+use snake_remix::bot::Bot;
+use snake_remix::controls::Action;
+use snake_remix::difficulty::Difficulty;
+use snake_remix::level::Level;
+use snake_remix::settings::{DisplayMode, Settings, BOARD_SIZES, MAX_MUSIC_VOLUME, MAX_SOUND_VOLUME};
+use snake_remix::spawn::SpawnPolicy;
+
+use super::controls::ControlsScreen;
+use super::menu::{Choice, Menu};
+use super::{draw_centered, label, window_mode, App, Screen, Transition, Vector2};
+
+const MUSIC: usize = 0;
+const MUSIC_VOLUME: usize = 1;
+const SOUND_VOLUME: usize = 2;
+const SPEED: usize = 3;
+const DISPLAY: usize = 4;
+const BOARD: usize = 5;
+const WALLS: usize = 6;
+const POWER_UPS: usize = 7;
+const FOOD: usize = 8;
+const DIFFICULTY: usize = 9;
+const MODE: usize = 10;
+const BOT: usize = 11;
+const CONTROLS: usize = 12;
+const BACK: usize = 13;
+
+/// Changes the settings. Every change is saved right away.
+pub struct OptionsScreen {
+    menu: Menu,
+}
+
+impl OptionsScreen {
+    pub fn new(app: &App) -> Self {
+        OptionsScreen {
+            menu: Menu::new(OptionsScreen::items(&app.settings)),
+        }
+    }
+
+    fn items(settings: &Settings) -> Vec<String> {
+        let on_off = |on| if on { "On" } else { "Off" };
+        vec![
+            format!("Music: {}", on_off(settings.music_on)),
+            format!("Music volume: {:.0}%", 100.0 * settings.music_volume),
+            format!("Sound volume: {:.0}%", 100.0 * settings.sound_volume),
+            format!("Speed: {:.2}x", settings.speed),
+            format!("Display: {:?}", settings.display),
+            format!("Board: {}x{}", settings.board_width, settings.board_height),
+            format!("Walls: {}", on_off(settings.walls)),
+            format!("Power-ups: {}", on_off(settings.power_ups)),
+            format!("Food: {}", settings.food.name().map_or_else(|| settings.food.to_string(), String::from)),
+            match settings.speed_curve {
+                Some(_) => "Difficulty: Custom".to_string(),
+                None => format!("Difficulty: {:?}", settings.difficulty),
+            },
+            format!("Mode: {}", settings.mode.name()),
+            format!("Computer: {}", settings.bot),
+            "Controls".to_string(),
+            "Back".to_string(),
+        ]
+    }
+
+    /// Changes the value of an item one step up or down.
+    fn change(&mut self, ctx: &mut Context, app: &mut App, item: usize, up: bool) {
+        let step = |value: f32, by: f32, min: f32, max: f32| {
+            let value = if up { value + by } else { value - by };
+            value.max(min).min(max)
+        };
+        let settings = &mut app.settings;
+        match item {
+            MUSIC => {
+                // Saves the settings itself.
+                app.toggle_music();
+                return;
+            }
+            MUSIC_VOLUME => {
+                settings.music_volume = step(settings.music_volume, 0.05, 0.0, MAX_MUSIC_VOLUME);
+                app.music.set_volume(settings.music_volume);
+            }
+            SOUND_VOLUME => {
+                settings.sound_volume = step(settings.sound_volume, 0.25, 0.0, MAX_SOUND_VOLUME);
+                app.hit_sound.set_volume(settings.sound_volume);
+                app.death_sound.set_volume(settings.sound_volume);
+            }
+            SPEED => {
+                settings.speed = f64::from(step(settings.speed as f32, 0.25, 0.25, 4.0));
+                app.speed = settings.speed;
+            }
+            DISPLAY => {
+                let modes = [DisplayMode::Windowed, DisplayMode::Borderless, DisplayMode::Fullscreen];
+                let current = modes.iter().position(|mode| *mode == settings.display).unwrap_or(0);
+                let next = if up { current + 1 } else { current + modes.len() - 1 };
+                settings.display = modes[next % modes.len()];
+                let size = Vector2::new(settings.window_width as f32, settings.window_height as f32);
+                if let Err(e) = graphics::set_mode(ctx, window_mode(settings.display, size)) {
+                    eprintln!("Could not change the display mode: {}", e);
+                }
+            }
+            BOARD => {
+                let size = (settings.board_width, settings.board_height);
+                let next = match BOARD_SIZES.iter().position(|s| *s == size) {
+                    Some(current) if up => (current + 1) % BOARD_SIZES.len(),
+                    Some(current) => (current + BOARD_SIZES.len() - 1) % BOARD_SIZES.len(),
+                    None => 2,
+                };
+                let (width, height) = BOARD_SIZES[next];
+                settings.board_width = width;
+                settings.board_height = height;
+                app.level = Level::open(settings.grid());
+            }
+            WALLS => {
+                settings.walls = !settings.walls;
+                app.level = Level::open(settings.grid());
+            }
+            POWER_UPS => settings.power_ups = !settings.power_ups,
+            FOOD => {
+                let presets = &SpawnPolicy::PRESETS;
+                let next = match presets.iter().position(|(_, policy)| *policy == settings.food) {
+                    Some(current) if up => (current + 1) % presets.len(),
+                    Some(current) => (current + presets.len() - 1) % presets.len(),
+                    None => 0,
+                };
+                settings.food = presets[next].1;
+            }
+            DIFFICULTY => {
+                // Picking a preset replaces a speed curve of the player's own.
+                let current = Difficulty::ALL.iter().position(|d| *d == settings.difficulty).unwrap_or(1);
+                let next = match settings.speed_curve.take() {
+                    Some(_) => current,
+                    None if up => current + 1,
+                    None => current + Difficulty::ALL.len() - 1,
+                };
+                settings.difficulty = Difficulty::ALL[next % Difficulty::ALL.len()];
+            }
+            MODE => settings.mode = settings.mode.cycle(up),
+            BOT => {
+                let current = Bot::ALL.iter().position(|bot| *bot == settings.bot).unwrap_or(0);
+                let next = if up { current + 1 } else { current + Bot::ALL.len() - 1 };
+                settings.bot = Bot::ALL[next % Bot::ALL.len()];
+            }
+            _ => return,
+        }
+        app.save_settings();
+    }
+}
+
+impl Screen for OptionsScreen {
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        self.menu.items = OptionsScreen::items(&app.settings);
+        let y = 0.15 * app.screen_size.y;
+        draw_centered(ctx, app, &label("OPTIONS".to_string(), 60.0), y)?;
+        let hint = label("Change with left and right".to_string(), 24.0);
+        draw_centered(ctx, app, &hint, y + 70.0)?;
+        self.menu.draw(ctx, app, y + 130.0)
+    }
+
+    fn action(&mut self, ctx: &mut Context, app: &mut App, action: Action) -> Transition {
+        match self.menu.action(action) {
+            Choice::Chosen(BACK) | Choice::Back => return Transition::Pop,
+            Choice::Chosen(CONTROLS) => return Transition::Push(Box::new(ControlsScreen::new())),
+            Choice::Quit => return Transition::Quit,
+            Choice::Chosen(item) | Choice::Right(item) => self.change(ctx, app, item, true),
+            Choice::Left(item) => self.change(ctx, app, item, false),
+            Choice::None => {}
+        }
+        Transition::None
+    }
+}
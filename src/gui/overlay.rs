@@ -0,0 +1,399 @@
+//! Screens shown on top of the game: pause, game over and entering a name for
+//! a new high score.
+
+use ggez::event::KeyCode;
+use ggez::graphics::{self, Color};
+use ggez::{Context, GameResult};
+
+use std::time::Duration;
+
+use snake_remix::controls::Action;
+use snake_remix::highscore::{self, Entry};
+use snake_remix::sim::{Cause, GameStates, Input, Simulation};
+
+use super::menu::{Choice, Menu};
+use super::game::PLAYER_NAMES;
+use super::scores::HighScoresScreen;
+use super::{draw_centered, key_hint, label, text, App, Screen, Transition, GREY, WHITE, YELLOW};
+
+/// Shown while the game is paused.
+pub struct PauseScreen {
+    text_pause: graphics::Text,
+}
+
+impl PauseScreen {
+    pub fn new() -> Self {
+        PauseScreen {
+            text_pause: label("PAUSED".to_string(), 100.0),
+        }
+    }
+}
+
+impl Screen for PauseScreen {
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        let y = 0.5 * app.screen_size.y - 0.5 * self.text_pause.height(ctx) as f32;
+        draw_centered(ctx, app, &self.text_pause, y)?;
+        let hint = format!(
+            "Resume: {}    Main menu: {}",
+            key_hint(app, Action::Pause),
+            key_hint(app, Action::Cancel)
+        );
+        draw_centered(ctx, app, &label(hint, 30.0), 0.5 * app.screen_size.y + 80.0)
+    }
+
+    fn action(&mut self, _ctx: &mut Context, app: &mut App, action: Action) -> Transition {
+        match action {
+            Action::Quit => Transition::Quit,
+            Action::Pause | Action::Confirm => {
+                if let Some(game) = &mut app.game {
+                    game.apply(Input::Pause);
+                    game.resume();
+                }
+                Transition::Pop
+            }
+            Action::Cancel => Transition::ToTitle,
+            Action::ToggleMusic => {
+                app.toggle_music();
+                Transition::None
+            }
+            _ => Transition::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+const PLAY_AGAIN: usize = 0;
+const HIGH_SCORES: usize = 1;
+const MAIN_MENU: usize = 2;
+const NEXT_STAGE: usize = 3;
+
+/// What a snake died of, to follow its name.
+fn cause_text(cause: Cause) -> &'static str {
+    match cause {
+        Cause::Itself => "ran into itself",
+        Cause::Wall => "hit a wall",
+        Cause::Opponent => "ran into the other snake",
+        Cause::Timeout => "ran out of time",
+        Cause::Starved => "starved",
+    }
+}
+
+/// How the game that just ended went. The best score is looked up when the
+/// game ends, before a new high score is entered.
+struct Summary {
+    points: i32,
+    length: usize,
+    eaten: u32,
+    time: Duration,
+    best: Option<i32>,
+}
+
+impl Summary {
+    fn new(app: &App) -> Option<Summary> {
+        let game = app.game.as_ref()?;
+        let snake = game.sim.snake();
+        Some(Summary {
+            points: snake.points,
+            length: snake.length(),
+            eaten: snake.eaten,
+            time: game.sim.game_time,
+            best: app.high_scores.best(&game.category()),
+        })
+    }
+
+    /// The score, length and time of the game on one line.
+    fn stats(&self) -> String {
+        let seconds = self.time.as_secs();
+        let minutes = self.time.as_secs_f32() / 60.0;
+        let per_minute = if minutes > 0.0 { self.eaten as f32 / minutes } else { 0.0 };
+        format!(
+            "Score: {}   Length: {}   Time: {}:{:02}   Food per minute: {:.1}",
+            self.points,
+            self.length,
+            seconds / 60,
+            seconds % 60,
+            per_minute
+        )
+    }
+
+    /// The score compared with the best one before it.
+    fn best(&self) -> String {
+        match self.best {
+            Some(best) if self.points > best => format!("New best! The best was {}", best),
+            None if self.points > 0 => "New best!".to_string(),
+            Some(best) => format!("Best: {} ({} to go)", best, best - self.points),
+            None => String::new(),
+        }
+    }
+}
+
+/// Shown when the game has ended: what the snake died of, a summary of the
+/// game, and whether to play again. After a round of a versus match it shows
+/// who won and the score of the match instead.
+pub struct GameOverScreen {
+    text_game_over: graphics::Text,
+    text_you_win: graphics::Text,
+    menu: Menu,
+    /// What each item of the menu does; versus matches have no high scores.
+    items: Vec<usize>,
+    summary: Option<Summary>,
+    /// Whether we already checked for a new high score.
+    checked_score: bool,
+    /// The stage of the campaign the game just unlocked, to tell the player.
+    unlocked: Option<String>,
+}
+
+impl GameOverScreen {
+    pub fn new(app: &App) -> Self {
+        let versus = app.game.as_ref().is_some_and(|game| game.sim.is_versus());
+        let mut items = if versus {
+            vec![PLAY_AGAIN, MAIN_MENU]
+        } else {
+            vec![PLAY_AGAIN, HIGH_SCORES, MAIN_MENU]
+        };
+        if GameOverScreen::next_stage(app).is_some() {
+            items.insert(0, NEXT_STAGE);
+        }
+        let names = items.iter().map(|item| match *item {
+            NEXT_STAGE => "Next stage",
+            PLAY_AGAIN if versus => "Next round",
+            PLAY_AGAIN => "Play again",
+            HIGH_SCORES => "High scores",
+            _ => "Main menu",
+        });
+        GameOverScreen {
+            // `TextFragment` stores a string, and optional parameters which will override those
+            // of `Text` itself. This allows inlining differently formatted lines, words,
+            // or even individual letters, into the same block of text.
+            text_game_over: text("GAME OVER".to_string(), 100.0, Color::new(1.0, 0.0, 0.0, 1.0)),
+            text_you_win: text("YOU WIN".to_string(), 100.0, YELLOW),
+            menu: Menu::new(names.map(String::from).collect()),
+            items,
+            summary: Summary::new(app),
+            checked_score: false,
+            unlocked: None,
+        }
+    }
+
+    /// The stage of the campaign that comes after the one just won, if any.
+    fn next_stage(app: &App) -> Option<usize> {
+        let won = app.game.as_ref().is_some_and(|game| game.sim.game_states == GameStates::Won);
+        let next = app.stage? + 1;
+        if won && next < app.campaign.stages.len() {
+            Some(next)
+        } else {
+            None
+        }
+    }
+
+    /// A line for every snake that died, naming what killed it.
+    fn causes(sim: &Simulation) -> Vec<String> {
+        sim.snakes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, snake)| {
+                let name = if sim.is_versus() { PLAYER_NAMES[i] } else { "You" };
+                snake.died.map(|cause| format!("{} {}", name, cause_text(cause)))
+            })
+            .collect()
+    }
+}
+
+impl Screen for GameOverScreen {
+    fn shows_demo(&self) -> bool {
+        true
+    }
+
+    fn update(&mut self, _ctx: &mut Context, app: &mut App) -> GameResult<Transition> {
+        if self.checked_score {
+            return Ok(Transition::None);
+        }
+        self.checked_score = true;
+
+        // Reaching the goal of a stage unlocks the next one.
+        if let Some(stage) = app.stage {
+            let won = app.game.as_ref().is_some_and(|game| game.sim.game_states == GameStates::Won);
+            if won && app.progress.complete(stage) {
+                app.save_progress();
+                self.unlocked = Some(match app.campaign.stages.get(stage + 1) {
+                    Some(next) => format!("Stage {} unlocked: {}", stage + 2, next.name),
+                    None => "Campaign complete!".to_string(),
+                });
+            }
+        }
+
+        // Watching a replay or a versus match does not earn a place in the table.
+        let game = match &app.game {
+            Some(game) if !game.is_watching() && !game.sim.is_versus() => game,
+            _ => return Ok(Transition::None),
+        };
+        if app.high_scores.qualifies(&game.category(), game.sim.snake().points) {
+            let name = app.player_name.clone();
+            Ok(Transition::Push(Box::new(NameEntryScreen::new(name))))
+        } else {
+            Ok(Transition::None)
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        let sim = match &app.game {
+            Some(game) => &game.sim,
+            None => return Ok(()),
+        };
+        // The title goes above the middle of the screen, the lines below it end
+        // just above the menu.
+        let middle = 0.5 * app.screen_size.y;
+        let mut lines: Vec<graphics::Text> = GameOverScreen::causes(sim)
+            .into_iter()
+            .map(|cause| label(cause, 30.0))
+            .collect();
+        if sim.is_versus() {
+            let (title, color) = match sim.winner {
+                Some(winner) => (format!("{} WINS", PLAYER_NAMES[winner].to_uppercase()), YELLOW),
+                None => ("DRAW".to_string(), WHITE),
+            };
+            let title = text(title, 100.0, color);
+            let y = middle - 80.0 - title.height(ctx) as f32;
+            draw_centered(ctx, app, &title, y)?;
+            let wins = &sim.scoreboard.wins;
+            let score = format!("{} {} : {} {}", PLAYER_NAMES[0], wins[0], wins[1], PLAYER_NAMES[1]);
+            lines.insert(0, label(score, 40.0));
+        } else {
+            let won = sim.game_states == GameStates::Won;
+            let title = if won { &self.text_you_win } else { &self.text_game_over };
+            let y = middle - 120.0 - title.height(ctx) as f32;
+            draw_centered(ctx, app, title, y)?;
+            if let Some(summary) = &self.summary {
+                lines.push(label(summary.stats(), 30.0));
+                lines.push(text(summary.best(), 30.0, GREY));
+            }
+            if let Some(unlocked) = &self.unlocked {
+                lines.push(text(unlocked.clone(), 30.0, YELLOW));
+            }
+        }
+        let mut y = middle + 40.0 - 40.0 * lines.len() as f32;
+        for line in &lines {
+            draw_centered(ctx, app, line, y)?;
+            y += 40.0;
+        }
+        self.menu.draw(ctx, app, middle + 80.0)
+    }
+
+    fn action(&mut self, ctx: &mut Context, app: &mut App, action: Action) -> Transition {
+        let choice = match self.menu.action(action) {
+            Choice::Chosen(i) => Choice::Chosen(self.items[i]),
+            choice => choice,
+        };
+        match choice {
+            Choice::Chosen(NEXT_STAGE) => {
+                if let Some(next) = GameOverScreen::next_stage(app) {
+                    if let Err(e) = app.play_stage(ctx, next) {
+                        eprintln!("Could not start stage {}: {}", next + 1, e);
+                        return Transition::None;
+                    }
+                }
+                Transition::Pop
+            }
+            Choice::Chosen(PLAY_AGAIN) => {
+                if let Some(game) = &mut app.game {
+                    game.apply(Input::Restart);
+                    game.resume();
+                }
+                Transition::Pop
+            }
+            Choice::Chosen(HIGH_SCORES) => {
+                let category = app.game.as_ref().map(|game| game.category());
+                Transition::Push(Box::new(HighScoresScreen::new(app, category, None)))
+            }
+            Choice::Chosen(MAIN_MENU) | Choice::Back => Transition::ToTitle,
+            Choice::Quit => Transition::Quit,
+            _ if action == Action::ToggleMusic => {
+                app.toggle_music();
+                Transition::None
+            }
+            _ => Transition::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+/// Asks for a name to put a new high score under.
+pub struct NameEntryScreen {
+    name: String,
+}
+
+impl NameEntryScreen {
+    pub fn new(name: String) -> Self {
+        NameEntryScreen { name }
+    }
+
+    /// Puts the score of the game that just ended into the high-score table
+    /// and shows the table.
+    fn enter(&mut self, app: &mut App) -> Transition {
+        let game = match &app.game {
+            Some(game) => game,
+            None => return Transition::Pop,
+        };
+        let name = match self.name.trim() {
+            "" => "Player",
+            name => name,
+        };
+        let snake = game.sim.snake();
+        let entry = Entry::new(name, snake.points, snake.length(), game.sim.seed);
+        let category = game.category();
+        let place = app.high_scores.insert(&category, entry);
+        app.player_name = name.to_string();
+        app.save_high_scores();
+        Transition::Replace(Box::new(HighScoresScreen::new(app, Some(category), place)))
+    }
+}
+
+impl Screen for NameEntryScreen {
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        let y = 0.5 * app.screen_size.y + 100.0;
+        let prompt = label(format!("New high score! Your name: {}_", self.name), 30.0);
+        draw_centered(ctx, app, &prompt, y)?;
+        let hint = label("Confirm: Enter    Skip: Escape".to_string(), 24.0);
+        draw_centered(ctx, app, &hint, y + 40.0)
+    }
+
+    // While a name is typed in, keys only edit the name.
+    fn key_down(&mut self, _ctx: &mut Context, app: &mut App, keycode: KeyCode) -> Transition {
+        match keycode {
+            KeyCode::Back => {
+                self.name.pop();
+                Transition::None
+            }
+            KeyCode::Return | KeyCode::NumpadEnter => self.enter(app),
+            KeyCode::Escape => Transition::Pop,
+            _ => Transition::None,
+        }
+    }
+
+    /// On a gamepad the name last entered is taken as it is.
+    fn action(&mut self, _ctx: &mut Context, app: &mut App, action: Action) -> Transition {
+        match action {
+            Action::Confirm => self.enter(app),
+            Action::Cancel => Transition::Pop,
+            _ => Transition::None,
+        }
+    }
+
+    /// Typed characters go into the name.
+    fn text_input(&mut self, _app: &mut App, character: char) -> Transition {
+        if !character.is_control() && self.name.chars().count() < highscore::MAX_NAME_LENGTH {
+            self.name.push(character);
+        }
+        Transition::None
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
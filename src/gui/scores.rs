@@ -0,0 +1,114 @@
+//! The high-score tables.
+
+use ggez::graphics::{self, Color};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+
+use snake_remix::controls::Action;
+use snake_remix::highscore;
+use snake_remix::sim::Direction;
+
+use super::{draw_centered, draw_shade, key_hint, label, text, App, Screen, Transition, GREY, WHITE, YELLOW};
+
+/// Shows the high-score table of one category at a time. Left and right switch
+/// between the categories that have scores.
+pub struct HighScoresScreen {
+    categories: Vec<String>,
+    current: usize,
+    /// The place of a score that was just entered.
+    highlight: Option<usize>,
+}
+
+impl HighScoresScreen {
+    /// Shows the table of `category`, or of the level the next game is played on.
+    pub fn new(app: &App, category: Option<String>, highlight: Option<usize>) -> Self {
+        let category = category.unwrap_or_else(|| highscore::category(&app.level, &app.settings.rules()));
+        let mut categories: Vec<String> = app.high_scores.categories().map(String::from).collect();
+        if !categories.contains(&category) {
+            categories.push(category.clone());
+            categories.sort();
+        }
+        let current = categories.iter().position(|c| *c == category).unwrap_or(0);
+        HighScoresScreen {
+            categories,
+            current,
+            highlight,
+        }
+    }
+}
+
+impl Screen for HighScoresScreen {
+    /// Draws the table, highlighting the score that was just entered.
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        draw_shade(ctx, app)?;
+
+        let category = &self.categories[self.current];
+        let center = 0.5 * app.screen_size.x;
+        let mut y = 0.5 * app.screen_size.y - 300.0;
+
+        draw_centered(ctx, app, &label("HIGH SCORES".to_string(), 60.0), y)?;
+        y += 70.0;
+        let heading = if self.categories.len() > 1 {
+            format!("<  {}  >", category)
+        } else {
+            category.clone()
+        };
+        draw_centered(ctx, app, &label(heading, 24.0), y)?;
+        y += 50.0;
+
+        // Place, name, points, length, date and seed each get a column.
+        let draw_row = |ctx: &mut Context, cells: [String; 6], color: Color, y: f32| -> GameResult {
+            let columns = [-400.0, -340.0, -60.0, 60.0, 180.0, 340.0];
+            for (x, cell) in columns.iter().zip(cells.iter()) {
+                graphics::draw(ctx, &text(cell.clone(), 28.0, color), (Point2::new(center + x, y),))?;
+            }
+            Ok(())
+        };
+        let headings = ["", "Name", "Points", "Length", "Date", "Seed"];
+        draw_row(ctx, headings.map(String::from), GREY, y)?;
+        y += 36.0;
+
+        let table = app.high_scores.table(category);
+        for (place, entry) in table.iter().enumerate() {
+            let cells = [
+                format!("{}.", place + 1),
+                entry.name.clone(),
+                entry.points.to_string(),
+                entry.length.to_string(),
+                entry.date(),
+                entry.seed.to_string(),
+            ];
+            let color = if self.highlight == Some(place) { YELLOW } else { WHITE };
+            draw_row(ctx, cells, color, y)?;
+            y += 36.0;
+        }
+        if table.is_empty() {
+            draw_centered(ctx, app, &label("No scores yet".to_string(), 28.0), y)?;
+        }
+
+        let hint = label(format!("Back: {}", key_hint(app, Action::Cancel)), 30.0);
+        draw_centered(ctx, app, &hint, 0.5 * app.screen_size.y + 300.0)
+    }
+
+    fn action(&mut self, _ctx: &mut Context, _app: &mut App, action: Action) -> Transition {
+        let count = self.categories.len();
+        match (action, action.direction()) {
+            (_, Some(Direction::Left)) => {
+                self.current = (self.current + count - 1) % count;
+                self.highlight = None;
+            }
+            (_, Some(Direction::Right)) => {
+                self.current = (self.current + 1) % count;
+                self.highlight = None;
+            }
+            (Action::Cancel, _) | (Action::Confirm, _) => return Transition::Pop,
+            (Action::Quit, _) => return Transition::Quit,
+            _ => {}
+        }
+        Transition::None
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,198 @@
+//! Drawing the board: where the grid goes on the screen, its tiles and the snakes.
+
+use ggez::graphics;
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+
+use snake_remix::level::Level;
+use snake_remix::sim::{Boundary, Grid, GridPosition, Snake};
+
+use super::Vector2;
+
+// Where the grid goes on the screen and how large its cells are drawn. This is
+// worked out from the actual window size, so nothing assumes one resolution.
+pub struct Layout {
+    // Pixel size of one grid cell
+    cell_size: f32,
+
+    // Grid pixel offset
+    offset: Vector2,
+}
+
+impl Layout {
+    // Space kept free above and below the grid for text
+    const HUD_HEIGHT: f32 = 24.0;
+
+    // Fit the grid into the screen, with room for a wall tile on every side. A
+    // requested cell size is used if it fits, otherwise the cells are made smaller.
+    pub fn new(screen: Vector2, grid: &Grid, cell_size: Option<f32>) -> Self {
+        let fit_x = screen.x / (grid.width as f32 + 2.0);
+        let fit_y = (screen.y - 2.0 * Layout::HUD_HEIGHT) / (grid.height as f32 + 2.0);
+        let fit = fit_x.min(fit_y).floor().max(1.0);
+        let cell_size = cell_size.map_or(fit, |size| size.min(fit));
+
+        // Get pixel size of grid calculated from number of grid cells and cell size
+        let pixel_size = cell_size * Vector2::new(grid.width as f32, grid.height as f32);
+        let offset = 0.5 * (screen - pixel_size);
+
+        Layout {
+            cell_size,
+            offset: Vector2::new(offset.x.floor(), offset.y.floor()),
+        }
+    }
+
+    // Transform and scale for sprites that were laid out `GridView::SPRITE_SIZE`
+    // pixels per cell, so that they land on the grid.
+    pub fn grid_param(&self) -> graphics::DrawParam {
+        let scale = self.cell_size / GridView::SPRITE_SIZE;
+        graphics::DrawParam::new()
+            .dest(Point2::new(self.offset.x, self.offset.y))
+            .scale(Vector2::new(scale, scale))
+    }
+
+    // Transform and scale for a single sprite drawn on a grid cell.
+    pub fn cell_param(&self, pos: GridPosition) -> graphics::DrawParam {
+        let scale = self.cell_size / GridView::SPRITE_SIZE;
+        graphics::DrawParam::new()
+            .dest(Point2::new(
+                self.offset.x + self.cell_size * pos.x as f32,
+                self.offset.y + self.cell_size * pos.y as f32,
+            ))
+            .scale(Vector2::new(scale, scale))
+    }
+}
+
+// Grid tiles
+pub struct GridView {
+    // Background tiles
+    spritebatch: graphics::spritebatch::SpriteBatch,
+
+    // Wall tiles
+    spritebatch2: graphics::spritebatch::SpriteBatch,
+}
+
+impl GridView {
+    pub fn new(ctx: &mut Context, level: &Level) -> Self {
+        let grid = &level.grid;
+
+        // Background tiles
+        let image = graphics::Image::new(ctx, "/png/element_grey_background.png").unwrap();
+        let mut batch = graphics::spritebatch::SpriteBatch::new(image);
+
+        // Add background tiles
+        for x in 0..grid.width {
+            for y in 0..grid.height {
+                let p = graphics::DrawParam::new()
+                    .dest(GridView::cell_dest(GridPosition::new(x, y)))
+                    .scale(Vector2::new(1.0, 1.0));
+                batch.add(p);
+            }
+        }
+
+        // Wall tiles
+        let image2 = graphics::Image::new(ctx, "/png/element_grey_square.png").unwrap();
+        let mut batch2 = graphics::spritebatch::SpriteBatch::new(image2);
+
+        // Add the walls of the level to spritebatch
+        for wall in level.walls() {
+            let p = graphics::DrawParam::new()
+                .dest(GridView::cell_dest(wall))
+                .scale(Vector2::new(1.0, 1.0));
+            batch2.add(p);
+        }
+
+        // The border around the grid is only drawn when it is solid
+        if grid.boundary == Boundary::Solid {
+            // Add left and right walls
+            for y in -1..grid.height + 1 {
+                // Add left wall
+                let p = graphics::DrawParam::new()
+                    .dest(GridView::cell_dest(GridPosition::new(-1, y)))
+                    .scale(Vector2::new(1.0, 1.0));
+                batch2.add(p);
+
+                // Add right wall
+                let p = graphics::DrawParam::new()
+                    .dest(GridView::cell_dest(GridPosition::new(grid.width, y)))
+                    .scale(Vector2::new(1.0, 1.0));
+                batch2.add(p);
+            }
+
+            // Add top and bottom walls
+            for x in 0..grid.width {
+                // Top wall
+                let p = graphics::DrawParam::new()
+                    .dest(GridView::cell_dest(GridPosition::new(x, -1)))
+                    .scale(Vector2::new(1.0, 1.0));
+                batch2.add(p);
+
+                // Bottom wall
+                let p = graphics::DrawParam::new()
+                    .dest(GridView::cell_dest(GridPosition::new(x, grid.height)))
+                    .scale(Vector2::new(1.0, 1.0));
+                batch2.add(p);
+            }
+        }
+
+        GridView {
+            spritebatch: batch,
+            spritebatch2: batch2,
+        }
+    }
+
+    // The pixel size of the tile images, which are 32x32 pixels. Sprite batches
+    // are laid out at this size and scaled to the actual cell size when drawn.
+    const SPRITE_SIZE: f32 = 32.0;
+
+    // Draw grid.
+    pub fn draw(&mut self, ctx: &mut Context, layout: &Layout) -> GameResult<()> {
+        // Draw background
+        graphics::draw(ctx, &self.spritebatch, layout.grid_param())?;
+
+        // Draw walls
+        graphics::draw(ctx, &self.spritebatch2, layout.grid_param())?;
+
+        Ok(())
+    }
+
+    /// Pixel position of the top left corner of a grid cell within a sprite batch.
+    fn cell_dest(pos: GridPosition) -> Point2<f32> {
+        Point2::new(
+            GridView::SPRITE_SIZE * pos.x as f32,
+            GridView::SPRITE_SIZE * pos.y as f32,
+        )
+    }
+}
+
+/// Here we have the Snake drawn using a SpriteBatch. Only the head and the
+/// `shown - 1` segments right behind it are drawn, tinted with `color`; this is
+/// how a snake that died flashes and crumbles away.
+pub fn draw_snake(
+    ctx: &mut Context,
+    snake: &Snake,
+    spritebatch: &mut graphics::spritebatch::SpriteBatch,
+    layout: &Layout,
+    shown: usize,
+    color: graphics::Color,
+) -> GameResult<()> {
+    if shown == 0 {
+        return Ok(());
+    }
+    // We first iterate through the body segments and draw them.
+    for seg in snake.body.iter().take(shown - 1) {
+        let p = graphics::DrawParam::new()
+            .dest(GridView::cell_dest(seg.pos))
+            .scale(Vector2::new(1.0, 1.0))
+            .color(color);
+        spritebatch.add(p);
+    }
+    let p = graphics::DrawParam::new()
+        .dest(GridView::cell_dest(snake.head.pos))
+        .scale(Vector2::new(1.0, 1.0))
+        .color(color);
+    spritebatch.add(p);
+
+    graphics::draw(ctx, spritebatch, layout.grid_param())?;
+    spritebatch.clear();
+    Ok(())
+}
@@ -0,0 +1,195 @@
+//! The best scores, kept between launches.
+//!
+//! Scores are only compared with scores made under the same rules, so there is
+//! one table per board configuration (see `category`). The tables are stored as
+//! TOML in the user's data directory, e.g.
+//! `~/.local/share/snake-remix/highscores.toml` on Linux.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::difficulty::{Difficulty, SpeedCurve};
+use crate::level::Level;
+use crate::mode::Mode;
+use crate::sim::Rules;
+use crate::spawn::SpawnPolicy;
+use crate::store::{self, StoreError};
+
+/// How many scores a table keeps.
+pub const TABLE_SIZE: usize = 10;
+
+/// The longest name that can be entered.
+pub const MAX_NAME_LENGTH: usize = 12;
+
+/// One score in a table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub points: i32,
+    /// Length of the snake at the end of the game.
+    pub length: usize,
+    /// The seed the game was played with, so it can be played again.
+    pub seed: u64,
+    /// When the game ended, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl Entry {
+    /// An entry for a game that ended just now.
+    pub fn new(name: &str, points: i32, length: usize, seed: u64) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Entry {
+            name: name.to_string(),
+            points,
+            length,
+            seed,
+            timestamp,
+        }
+    }
+
+    /// The day the game ended on, like `2020-05-17`.
+    pub fn date(&self) -> String {
+        // Days since the epoch to a civil date, from Howard Hinnant's date algorithms.
+        let days = (self.timestamp / 86400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// The name of the table that scores made on `level` under `rules` go into.
+/// Scores made with power-ups, food that turns up differently, at another
+/// difficulty than normal or in another mode than classic get tables of their
+/// own.
+pub fn category(level: &Level, rules: &Rules) -> String {
+    let grid = &level.grid;
+    let mut category = format!("{} {}x{} {}", level.name, grid.width, grid.height, grid.boundary);
+    if rules.power_ups {
+        category.push_str(" power-ups");
+    }
+    if rules.food != SpawnPolicy::default() {
+        match rules.food.name() {
+            Some(name) => category.push_str(&format!(" {}", name.to_lowercase())),
+            None => category.push_str(&format!(" food {}", rules.food)),
+        }
+    }
+    if rules.speed != SpeedCurve::default() {
+        match Difficulty::of(rules.speed) {
+            Some(difficulty) => category.push_str(&format!(" {}", difficulty)),
+            None => category.push_str(&format!(" speed {}", rules.speed)),
+        }
+    }
+    if rules.mode != Mode::default() {
+        category.push_str(&format!(" {}", rules.mode));
+    }
+    category
+}
+
+/// All high-score tables, by category.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScores {
+    #[serde(default)]
+    tables: BTreeMap<String, Vec<Entry>>,
+}
+
+impl HighScores {
+    /// Where the high scores are kept, if the platform has a data directory.
+    pub fn path() -> Option<PathBuf> {
+        crate::project_dirs().map(|dirs| dirs.data_dir().join("highscores.toml"))
+    }
+
+    /// Loads the high scores at `path`, starting with empty tables when there are
+    /// none yet or they cannot be read.
+    pub fn load_or_default(path: Option<&Path>) -> HighScores {
+        store::load_or_default(path, "high scores")
+    }
+
+    /// Writes the high scores to `path`, creating its directory if needed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), StoreError> {
+        store::save(path, self)
+    }
+
+    /// All categories that have scores.
+    pub fn categories(&self) -> impl Iterator<Item = &str> {
+        self.tables.keys().map(String::as_str)
+    }
+
+    /// The scores in a category, best first.
+    pub fn table(&self, category: &str) -> &[Entry] {
+        self.tables.get(category).map_or(&[], |table| table.as_slice())
+    }
+
+    /// The best score in a category so far.
+    pub fn best(&self, category: &str) -> Option<i32> {
+        self.table(category).first().map(|entry| entry.points)
+    }
+
+    /// Whether `points` would make it into the table of a category.
+    pub fn qualifies(&self, category: &str, points: i32) -> bool {
+        let table = self.table(category);
+        points > 0 && (table.len() < TABLE_SIZE || table.last().is_some_and(|last| points > last.points))
+    }
+
+    /// Adds a score to the table of a category and returns its place in the
+    /// table, counting from 0, or `None` if it did not make it in. Equal scores
+    /// keep the order they were made in.
+    pub fn insert(&mut self, category: &str, entry: Entry) -> Option<usize> {
+        if !self.qualifies(category, entry.points) {
+            return None;
+        }
+        let table = self.tables.entry(category.to_string()).or_default();
+        let place = table
+            .iter()
+            .position(|other| entry.points > other.points)
+            .unwrap_or(table.len());
+        table.insert(place, entry);
+        table.truncate(TABLE_SIZE);
+        Some(place)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, points: i32) -> Entry {
+        Entry::new(name, points, 3, 1)
+    }
+
+    #[test]
+    fn scores_are_kept_best_first_in_the_order_they_were_made() {
+        let mut scores = HighScores::default();
+        assert_eq!(scores.insert("open", entry("a", 5)), Some(0));
+        assert_eq!(scores.insert("open", entry("b", 9)), Some(0));
+        assert_eq!(scores.insert("open", entry("c", 5)), Some(2));
+        assert_eq!(scores.insert("open", entry("d", 0)), None);
+        let names: Vec<&str> = scores.table("open").iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["b", "a", "c"]);
+        assert_eq!(scores.best("open"), Some(9));
+        assert_eq!(scores.best("arena"), None);
+    }
+
+    #[test]
+    fn tables_are_capped() {
+        let mut scores = HighScores::default();
+        for points in 1..=TABLE_SIZE as i32 {
+            scores.insert("open", entry("a", points));
+        }
+        assert_eq!(scores.insert("open", entry("low", 1)), None);
+        assert_eq!(scores.insert("open", entry("high", 100)), Some(0));
+        let table = scores.table("open");
+        assert_eq!(table.len(), TABLE_SIZE);
+        assert_eq!(table.last().map(|entry| entry.points), Some(2));
+    }
+}
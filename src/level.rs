@@ -0,0 +1,472 @@
+//! Levels: the layout of the board a game is played on.
+//!
+//! Levels are plain text files in `resources/levels`, so new ones can be made
+//! without recompiling. Empty lines and lines starting with `#` are ignored
+//! until the map starts:
+//!
+//! ```text
+//! # A small arena
+//! name Arena
+//! boundary solid
+//! map
+//! ..........
+//! .>....**..
+//! ...##.....
+//! ..........
+//! ```
+//!
+//! * `name <text>` - optional, shown to the player. Defaults to the file name.
+//! * `boundary <wrap|solid>` - optional, what happens at the edges of the
+//!   board. Defaults to `wrap`.
+//! * `goal <length|points> <n>` - optional, what it takes to win the level:
+//!   growing the snake to `n` cells or scoring `n` points. Only counts in
+//!   games for one player. Without a goal the game goes on until the board is
+//!   full, like on an open board.
+//! * `map` - every line after this one is a row of the board, and all rows
+//!   must be equally long. The board is as wide as the rows and as high as
+//!   there are rows. Each character is one cell:
+//!   * `.` - an empty cell.
+//!   * `#` - a wall. Running into it is lethal.
+//!   * `>`, `<`, `^`, `v` - where a snake starts and the direction it starts
+//!     moving in. The first one (reading left to right, top to bottom) is the
+//!     first player's snake, the second one the second player's. Without any,
+//!     the snake starts a quarter of the way across the board moving right.
+//!     Without a second one, the second player starts opposite the first, or
+//!     on a free cell near the bottom right corner if something is in the way.
+//!   * `*` - a cell where food may spawn. Without any, food spawns anywhere.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::sim::{Boundary, Direction, Grid, GridPosition, Snake};
+
+/// Where a snake starts out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Start {
+    pub pos: GridPosition,
+    pub dir: Direction,
+}
+
+impl Start {
+    /// Where the tail of a new snake is: right behind its head, round the edge
+    /// on a board that wraps.
+    pub fn tail(self, grid: &Grid) -> GridPosition {
+        GridPosition::new_from_move(self.pos, self.dir.inverse(), grid)
+    }
+}
+
+/// What a snake has to do to win a level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    /// Grow to this many cells, head included.
+    Length(usize),
+    /// Score this many points.
+    Points(i32),
+}
+
+impl Goal {
+    pub fn is_reached(self, snake: &Snake) -> bool {
+        match self {
+            Goal::Length(length) => snake.length() >= length,
+            Goal::Points(points) => snake.points >= points,
+        }
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Goal::Length(length) => write!(f, "length {}", length),
+            Goal::Points(points) => write!(f, "points {}", points),
+        }
+    }
+}
+
+impl FromStr for Goal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let number = |field: &str| -> Result<u32, String> {
+            match field.parse() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(format!("expected a positive number, found `{}`", field)),
+            }
+        };
+        match fields.as_slice() {
+            ["length", n] => Ok(Goal::Length(number(n)? as usize)),
+            ["points", n] => Ok(Goal::Points(number(n)?.min(i32::MAX as u32) as i32)),
+            _ => Err(format!("expected a goal like `length 20` or `points 15`, found `{}`", s)),
+        }
+    }
+}
+
+/// The board a game is played on: its size, boundary rule, walls, where the
+/// snakes start and where food may spawn.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Level {
+    pub name: String,
+    pub grid: Grid,
+    /// One entry per cell, row by row.
+    walls: Vec<bool>,
+    pub starts: Vec<Start>,
+    /// Cells food may spawn on. Empty means anywhere.
+    pub food_zone: Vec<GridPosition>,
+    /// What it takes to win, if anything short of filling the board.
+    pub goal: Option<Goal>,
+}
+
+/// Things that can go wrong while loading a level.
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Io(e) => write!(f, "{}", e),
+            LevelError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for LevelError {}
+
+impl From<io::Error> for LevelError {
+    fn from(e: io::Error) -> Self {
+        LevelError::Io(e)
+    }
+}
+
+impl Level {
+    /// An empty board without any walls inside, like the original game.
+    pub fn open(grid: Grid) -> Self {
+        Level {
+            name: "Open".to_string(),
+            grid,
+            walls: vec![false; grid.width as usize * grid.height as usize],
+            starts: vec![Start {
+                // We put our snake a quarter of the way across our grid in the x axis
+                // and half way down the y axis. This works well since we start out moving to the right.
+                pos: GridPosition::new(grid.width / 4, grid.height / 2),
+                dir: Direction::Right,
+            }],
+            food_zone: Vec::new(),
+            goal: None,
+        }
+    }
+
+    /// The path of the level called `name` in the resources directory.
+    pub fn path(name: &str) -> PathBuf {
+        crate::resource_dir().join("levels").join(format!("{}.txt", name))
+    }
+
+    /// The names of all levels in the resources directory, sorted.
+    pub fn names() -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(crate::resource_dir().join("levels"))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+                    .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// Loads the level called `name` from the resources directory.
+    pub fn find(name: &str) -> Result<Level, LevelError> {
+        Level::load(Level::path(name))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Level, LevelError> {
+        let text = fs::read_to_string(&path)?;
+        let mut level = Level::parse(&text)?;
+        if level.name.is_empty() {
+            if let Some(stem) = path.as_ref().file_stem() {
+                level.name = stem.to_string_lossy().into_owned();
+            }
+        }
+        Ok(level)
+    }
+
+    pub fn parse(text: &str) -> Result<Level, LevelError> {
+        let mut name = String::new();
+        let mut boundary = Boundary::Wrap;
+        let mut goal = None;
+        let mut lines = text.lines().enumerate();
+
+        // First the header, up to the `map` line.
+        let mut map_line = 0;
+        for (i, line) in lines.by_ref() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| LevelError::Parse {
+                line: i + 1,
+                message,
+            };
+            let (key, value) = match line.find(char::is_whitespace) {
+                Some(at) => (&line[..at], line[at..].trim()),
+                None => (line, ""),
+            };
+            match key {
+                "name" => name = value.to_string(),
+                "boundary" => boundary = value.parse().map_err(error)?,
+                "goal" => goal = Some(value.parse().map_err(error)?),
+                "map" => {
+                    map_line = i + 1;
+                    break;
+                }
+                other => return Err(error(format!("unknown record `{}`", other))),
+            }
+        }
+        if map_line == 0 {
+            return Err(LevelError::Parse {
+                line: 0,
+                message: "no map given".to_string(),
+            });
+        }
+
+        let rows: Vec<(usize, &str)> = lines.map(|(i, line)| (i + 1, line.trim_end())).collect();
+        let mut level = Level::from_rows(&rows, boundary)?;
+        level.name = name;
+        level.goal = goal;
+        Ok(level)
+    }
+
+    /// Builds a level from map rows, each with the line number it came from.
+    pub fn from_rows(rows: &[(usize, &str)], boundary: Boundary) -> Result<Level, LevelError> {
+        // Trailing empty lines are not part of the map.
+        let rows = match rows.iter().rposition(|(_, row)| !row.is_empty()) {
+            Some(last) => &rows[..=last],
+            None => &[],
+        };
+        let width = rows.first().map_or(0, |(_, row)| row.chars().count());
+        if width == 0 {
+            return Err(LevelError::Parse {
+                line: rows.first().map_or(0, |(line, _)| *line),
+                message: "the map is empty".to_string(),
+            });
+        }
+        let grid = Grid::sized(width as u64, rows.len() as u64).map_err(|message| LevelError::Parse {
+            line: rows[0].0,
+            message,
+        })?;
+        let grid = Grid { boundary, ..grid };
+
+        let mut level = Level::open(grid);
+        level.starts.clear();
+        for (y, (line, row)) in rows.iter().enumerate() {
+            let error = |message: String| LevelError::Parse {
+                line: *line,
+                message,
+            };
+            if row.chars().count() != width {
+                return Err(error(format!("expected {} cells, found {}", width, row.chars().count())));
+            }
+            for (x, c) in row.chars().enumerate() {
+                let pos = GridPosition::new(x as i16, y as i16);
+                let start = |dir| Start { pos, dir };
+                match c {
+                    '.' => {}
+                    '#' => level.walls[y * width + x] = true,
+                    '*' => level.food_zone.push(pos),
+                    '>' => level.starts.push(start(Direction::Right)),
+                    '<' => level.starts.push(start(Direction::Left)),
+                    '^' => level.starts.push(start(Direction::Up)),
+                    'v' => level.starts.push(start(Direction::Down)),
+                    other => return Err(error(format!("unknown cell `{}`", other))),
+                }
+            }
+        }
+        if level.starts.is_empty() {
+            level.starts = Level::open(grid).starts;
+        }
+        level.check().map_err(|message| LevelError::Parse { line: 0, message })?;
+        Ok(level)
+    }
+
+    /// The map as text, one row per entry, in the format `from_rows` reads.
+    pub fn rows(&self) -> Vec<String> {
+        (0..self.grid.height)
+            .map(|y| {
+                (0..self.grid.width)
+                    .map(|x| {
+                        let pos = GridPosition::new(x, y);
+                        match self.starts.iter().find(|start| start.pos == pos) {
+                            Some(start) => match start.dir {
+                                Direction::Right => '>',
+                                Direction::Left => '<',
+                                Direction::Up => '^',
+                                Direction::Down => 'v',
+                            },
+                            None if self.is_wall(pos) => '#',
+                            None if self.food_zone.contains(&pos) => '*',
+                            None => '.',
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Where the snakes of `players` players start. Players the level has no
+    /// start for start opposite the first player (see `Level::opposite`).
+    pub fn starts_for(&self, players: usize) -> Vec<Start> {
+        let opposite = self.opposite(self.starts[0]);
+        (0..players)
+            .map(|i| self.starts.get(i).cloned().unwrap_or(opposite))
+            .collect()
+    }
+
+    /// Where a snake starts opposite `first`: mirrored through the middle of the
+    /// board, heading the other way. If a wall or `first` is in the way there,
+    /// it starts on the free cell nearest to the bottom right corner with room
+    /// to move on instead. If there is no such cell either, the mirrored start
+    /// is kept and `Level::check` rejects the level.
+    fn opposite(&self, first: Start) -> Start {
+        let taken = [first.pos, first.tail(&self.grid)];
+        let is_free = |pos: GridPosition| self.is_passable(pos) && !taken.contains(&pos);
+        let mirrored = Start {
+            pos: GridPosition::new(
+                self.grid.width - 1 - first.pos.x,
+                self.grid.height - 1 - first.pos.y,
+            ),
+            dir: first.dir.inverse(),
+        };
+        if is_free(mirrored.pos) && is_free(mirrored.tail(&self.grid)) {
+            return mirrored;
+        }
+        let dirs = [
+            first.dir.inverse(),
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ];
+        (0..self.grid.height)
+            .rev()
+            .flat_map(|y| (0..self.grid.width).rev().map(move |x| GridPosition::new(x, y)))
+            .flat_map(|pos| dirs.iter().map(move |&dir| Start { pos, dir }))
+            .find(|start| {
+                let ahead = GridPosition::new_from_move(start.pos, start.dir, &self.grid);
+                is_free(start.pos) && is_free(start.tail(&self.grid)) && is_free(ahead)
+            })
+            .unwrap_or(mirrored)
+    }
+
+    /// Makes sure a game can be set up on the level: the heads and tails of the
+    /// snakes of a versus match are on free cells of the board, apart from each
+    /// other, and there is room left for food.
+    pub fn check(&self) -> Result<(), String> {
+        let mut taken = Vec::new();
+        for start in self.starts_for(2) {
+            for pos in [start.pos, start.tail(&self.grid)] {
+                if !self.is_passable(pos) {
+                    return Err(format!("a snake would start off the board or on a wall at {}", pos));
+                }
+                if taken.contains(&pos) {
+                    return Err(format!("two snakes would start at {}", pos));
+                }
+                taken.push(pos);
+            }
+        }
+        let free = (0..self.grid.height)
+            .flat_map(|y| (0..self.grid.width).map(move |x| GridPosition::new(x, y)))
+            .any(|pos| self.is_passable(pos) && !taken.contains(&pos));
+        if free {
+            Ok(())
+        } else {
+            Err("there is no room for food".to_string())
+        }
+    }
+
+    /// Whether this is a plain board that can be described by its size alone.
+    pub fn is_open(&self) -> bool {
+        *self == Level {
+            name: self.name.clone(),
+            ..Level::open(self.grid)
+        }
+    }
+
+    /// Whether there is a wall inside the board at `pos`.
+    pub fn is_wall(&self, pos: GridPosition) -> bool {
+        self.grid.contains(pos) && self.walls[pos.y as usize * self.grid.width as usize + pos.x as usize]
+    }
+
+    /// Whether a snake can move onto `pos` without hitting a wall.
+    pub fn is_passable(&self, pos: GridPosition) -> bool {
+        self.grid.contains(pos) && !self.is_wall(pos)
+    }
+
+    /// All wall cells inside the board.
+    pub fn walls(&self) -> impl Iterator<Item = GridPosition> + '_ {
+        let width = self.grid.width as usize;
+        self.walls
+            .iter()
+            .enumerate()
+            .filter(|(_, wall)| **wall)
+            .map(move |(i, _)| GridPosition::new((i % width) as i16, (i / width) as i16))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_round_trip() {
+        for name in Level::names() {
+            let level = Level::find(&name).unwrap();
+            let rows = level.rows();
+            let numbered: Vec<(usize, &str)> = rows.iter().map(String::as_str).enumerate().collect();
+            let parsed = Level::from_rows(&numbered, level.grid.boundary).unwrap();
+            assert_eq!(parsed, Level { name: "Open".to_string(), goal: None, ..level });
+        }
+    }
+
+    #[test]
+    fn goal_round_trip() {
+        for goal in [Goal::Length(20), Goal::Points(15)] {
+            assert_eq!(goal.to_string().parse(), Ok(goal));
+        }
+        assert!("length 0".parse::<Goal>().is_err());
+        assert!("speed 3".parse::<Goal>().is_err());
+    }
+
+    #[test]
+    fn second_snake_starts_opposite_the_first() {
+        let level = Level::parse("boundary solid\nmap\n......\n.>....\n......\n......\n").unwrap();
+        assert_eq!(level.starts_for(2)[1], Start {
+            pos: GridPosition::new(4, 2),
+            dir: Direction::Left,
+        });
+    }
+
+    #[test]
+    fn second_snake_starts_elsewhere_when_a_wall_is_in_the_way() {
+        let level = Level::parse("boundary solid\nmap\n......\n.>....\n....#.\n......\n").unwrap();
+        let second = level.starts_for(2)[1];
+        assert_eq!(second, Start {
+            pos: GridPosition::new(4, 3),
+            dir: Direction::Left,
+        });
+    }
+
+    #[test]
+    fn levels_without_room_are_rejected() {
+        assert!(Level::parse("map\n.>\n..\n").is_err());
+        assert!(Level::parse("map\n####\n####\n####\n####\n").is_err());
+        assert!(Level::parse("boundary solid\nmap\n>...\n....\n....\n....\n").is_err());
+    }
+}
@@ -0,0 +1,49 @@
+//! Snake Remix
+//!
+//! The game rules live in this library so that they can run without a window.
+//! The ggez front-end in `main.rs` only translates input into `Input`s, calls
+//! `Simulation::step` and draws whatever state the simulation is in.
+//!
+//! Original author
+//! Author: @termhn
+//! Original repo: https://github.com/termhn/ggez_snake
+//!
+//! Edited by Joonas Lampinen 2020
+
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+
+pub mod bot;
+pub mod campaign;
+pub mod clock;
+pub mod controls;
+pub mod difficulty;
+pub mod env;
+pub mod highscore;
+pub mod level;
+pub mod mode;
+pub mod powerup;
+pub mod replay;
+pub mod settings;
+pub mod sim;
+pub mod spawn;
+pub mod store;
+
+/// The directory holding images, sounds and levels. When run through cargo this
+/// is the `resources` directory of the crate, otherwise `./resources`.
+pub fn resource_dir() -> PathBuf {
+    if let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") {
+        let mut path = PathBuf::from(manifest_dir);
+        path.push("resources");
+        path
+    } else {
+        PathBuf::from("./resources")
+    }
+}
+
+/// Where the game keeps its settings and high scores, if the platform has such
+/// directories.
+pub fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "snake-remix")
+}
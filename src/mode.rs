@@ -0,0 +1,116 @@
+//! The modes a game can be played in, each with its own way to win or lose.
+//!
+//! * `classic` - the game speeds up as the snake eats, and is lost when it runs
+//!   into something.
+//! * `time-attack` - eat as much as possible in two minutes of game time. The
+//!   game is won if the snake is still alive when the time is up.
+//! * `survival` - plain food rots away after eight seconds and turns up
+//!   elsewhere, and a snake that goes ten seconds without eating starves.
+//! * `endless` - the game never speeds up, so it can go on for as long as the
+//!   snake stays out of trouble.
+//!
+//! Only filling the whole board wins a game of any mode but time attack.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// The game modes to choose from in the options.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    #[default]
+    Classic,
+    TimeAttack,
+    Survival,
+    Endless,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 4] = [Mode::Classic, Mode::TimeAttack, Mode::Survival, Mode::Endless];
+
+    /// The name shown in the options and on the HUD.
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Classic => "Classic",
+            Mode::TimeAttack => "Time attack",
+            Mode::Survival => "Survival",
+            Mode::Endless => "Endless",
+        }
+    }
+
+    /// The mode after this one in `Mode::ALL`, or the one before it if not
+    /// `forward`, going round at the ends.
+    pub fn cycle(self, forward: bool) -> Mode {
+        let current = Mode::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        let next = if forward { current + 1 } else { current + Mode::ALL.len() - 1 };
+        Mode::ALL[next % Mode::ALL.len()]
+    }
+
+    /// How much game time there is before the game ends, if it is limited.
+    pub fn time_limit(self) -> Option<Duration> {
+        match self {
+            Mode::TimeAttack => Some(Duration::from_secs(120)),
+            _ => None,
+        }
+    }
+
+    /// How long a snake can go without eating before it starves, if it can.
+    pub fn starvation(self) -> Option<Duration> {
+        match self {
+            Mode::Survival => Some(Duration::from_secs(10)),
+            _ => None,
+        }
+    }
+
+    /// How long plain food stays on the board before it rots, if it does.
+    pub fn food_decay(self) -> Option<Duration> {
+        match self {
+            Mode::Survival => Some(Duration::from_secs(8)),
+            _ => None,
+        }
+    }
+
+    /// Whether the game gets faster as the snakes eat.
+    pub fn speeds_up(self) -> bool {
+        self != Mode::Endless
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Classic => "classic",
+            Mode::TimeAttack => "time-attack",
+            Mode::Survival => "survival",
+            Mode::Endless => "endless",
+        })
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mode::ALL
+            .iter()
+            .find(|mode| mode.to_string() == s)
+            .cloned()
+            .ok_or_else(|| format!("unknown mode `{}`", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        for mode in Mode::ALL.iter() {
+            assert_eq!(mode.to_string().parse(), Ok(*mode));
+        }
+        assert!("Classic".parse::<Mode>().is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! Food that does more than make the snake grow.
+//!
+//! With power-ups switched on in the `Rules`, every new piece of food is picked
+//! at random from the kinds below, by their spawn weights. Everything but plain
+//! food only stays on the board for a while before plain food takes its place.
+//!
+//! | kind         | weight | points | on the board | effect                          |
+//! |--------------|--------|--------|--------------|---------------------------------|
+//! | `plain`      | 70     | 1      | until eaten  | -                               |
+//! | `bonus`      | 10     | 5      | 6 s          | -                               |
+//! | `speed`      | 5      | 1      | 10 s         | the game runs faster for 8 s    |
+//! | `slow`       | 5      | 1      | 10 s         | the game runs slower for 8 s    |
+//! | `shrink`     | 4      | 1      | 10 s         | the tail loses 3 segments       |
+//! | `ghost`      | 3      | 1      | 10 s         | the snake may cross itself, 6 s |
+//! | `multiplier` | 3      | 1      | 10 s         | food is worth double for 15 s   |
+//!
+//! Times are game time, the sum of the intervals of the ticks played, so they
+//! run at the same pace as the game itself.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::sim::GameRng;
+
+/// The kinds of food that can be on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FoodKind {
+    Plain,
+    /// Worth more points, if eaten in time.
+    Bonus,
+    Speed,
+    Slow,
+    Shrink,
+    Ghost,
+    Multiplier,
+}
+
+impl FoodKind {
+    pub const ALL: [FoodKind; 7] = [
+        FoodKind::Plain,
+        FoodKind::Bonus,
+        FoodKind::Speed,
+        FoodKind::Slow,
+        FoodKind::Shrink,
+        FoodKind::Ghost,
+        FoodKind::Multiplier,
+    ];
+
+    /// How often this kind is picked, relative to the others.
+    pub fn weight(self) -> u32 {
+        match self {
+            FoodKind::Plain => 70,
+            FoodKind::Bonus => 10,
+            FoodKind::Speed | FoodKind::Slow => 5,
+            FoodKind::Shrink => 4,
+            FoodKind::Ghost | FoodKind::Multiplier => 3,
+        }
+    }
+
+    /// Points for eating it, before any multiplier.
+    pub fn points(self) -> i32 {
+        match self {
+            FoodKind::Bonus => 5,
+            _ => 1,
+        }
+    }
+
+    /// How long it stays on the board if nobody eats it, or `None` for ever.
+    pub fn lifetime(self) -> Option<Duration> {
+        match self {
+            FoodKind::Plain => None,
+            FoodKind::Bonus => Some(Duration::from_secs(6)),
+            _ => Some(Duration::from_secs(10)),
+        }
+    }
+
+    /// The effect eating it has on the snake, and for how long.
+    pub fn effect(self) -> Option<(Effect, Duration)> {
+        match self {
+            FoodKind::Speed => Some((Effect::Speed, Duration::from_secs(8))),
+            FoodKind::Slow => Some((Effect::Slow, Duration::from_secs(8))),
+            FoodKind::Ghost => Some((Effect::Ghost, Duration::from_secs(6))),
+            FoodKind::Multiplier => Some((Effect::Multiplier, Duration::from_secs(15))),
+            FoodKind::Plain | FoodKind::Bonus | FoodKind::Shrink => None,
+        }
+    }
+
+    /// Picks a kind at random by the spawn weights.
+    pub fn random(rng: &mut GameRng) -> FoodKind {
+        let total: u32 = FoodKind::ALL.iter().map(|kind| kind.weight()).sum();
+        let mut n = rng.gen_range(0, total);
+        for kind in FoodKind::ALL.iter() {
+            if n < kind.weight() {
+                return *kind;
+            }
+            n -= kind.weight();
+        }
+        FoodKind::Plain
+    }
+}
+
+impl fmt::Display for FoodKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            FoodKind::Plain => "plain",
+            FoodKind::Bonus => "bonus",
+            FoodKind::Speed => "speed",
+            FoodKind::Slow => "slow",
+            FoodKind::Shrink => "shrink",
+            FoodKind::Ghost => "ghost",
+            FoodKind::Multiplier => "multiplier",
+        })
+    }
+}
+
+impl FromStr for FoodKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FoodKind::ALL
+            .iter()
+            .find(|kind| kind.to_string() == s)
+            .cloned()
+            .ok_or_else(|| format!("unknown food `{}`", s))
+    }
+}
+
+/// Number of tail segments a `FoodKind::Shrink` takes off.
+pub const SHRINK_BY: usize = 3;
+
+/// An effect a snake can be under for a while.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Effect {
+    /// The game runs faster.
+    Speed,
+    /// The game runs slower.
+    Slow,
+    /// Running into its own body is not lethal.
+    Ghost,
+    /// Food is worth double.
+    Multiplier,
+}
+
+impl Effect {
+    /// What the tick interval is multiplied by while any snake is under this effect.
+    pub fn time_scale(self) -> f32 {
+        match self {
+            Effect::Speed => 0.6,
+            Effect::Slow => 1.6,
+            Effect::Ghost | Effect::Multiplier => 1.0,
+        }
+    }
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Effect::Speed => "Speed",
+            Effect::Slow => "Slow",
+            Effect::Ghost => "Ghost",
+            Effect::Multiplier => "x2",
+        })
+    }
+}
+
+/// The effects a snake is under, with the game time each one has left.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Effects {
+    active: Vec<(Effect, Duration)>,
+}
+
+impl Effects {
+    /// Puts the snake under `effect` for `duration`. Eating the same power-up
+    /// again starts its time over.
+    pub fn add(&mut self, effect: Effect, duration: Duration) {
+        self.active.retain(|(e, _)| *e != effect);
+        self.active.push((effect, duration));
+    }
+
+    pub fn is_active(&self, effect: Effect) -> bool {
+        self.active.iter().any(|(e, _)| *e == effect)
+    }
+
+    /// The active effects and the time they have left, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = (Effect, Duration)> + '_ {
+        self.active.iter().cloned()
+    }
+
+    /// Lets `elapsed` game time pass, ending the effects that run out.
+    pub fn advance(&mut self, elapsed: Duration) {
+        for (_, left) in self.active.iter_mut() {
+            *left = left.checked_sub(elapsed).unwrap_or_default();
+        }
+        self.active.retain(|(_, left)| *left > Duration::from_millis(0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+    use crate::sim::{Food, Grid, GridPosition, Simulation};
+
+    #[test]
+    fn effects_end_after_their_duration() {
+        for kind in FoodKind::ALL.iter() {
+            let (effect, duration) = match kind.effect() {
+                Some(effect) => effect,
+                None => continue,
+            };
+            let mut effects = Effects::default();
+            effects.add(effect, duration);
+            effects.advance(duration - Duration::from_millis(1));
+            assert!(effects.is_active(effect), "{} ended early", effect);
+            effects.advance(Duration::from_millis(1));
+            assert!(!effects.is_active(effect), "{} did not end", effect);
+        }
+    }
+
+    #[test]
+    fn eating_again_starts_the_time_over() {
+        let mut effects = Effects::default();
+        effects.add(Effect::Ghost, Duration::from_secs(6));
+        effects.advance(Duration::from_secs(5));
+        effects.add(Effect::Ghost, Duration::from_secs(6));
+        effects.advance(Duration::from_secs(5));
+        assert!(effects.is_active(Effect::Ghost));
+        assert_eq!(effects.iter().count(), 1);
+    }
+
+    #[test]
+    fn multiplier_doubles_food_only_while_it_lasts() {
+        let mut sim = Simulation::new(Level::open(Grid::new(40, 4)), 1);
+        let head = sim.snake().head.pos;
+        let ahead = |n: i16| GridPosition::new(head.x + n, head.y);
+        sim.foods = vec![
+            Food::with_kind(ahead(1), FoodKind::Multiplier),
+            Food::with_kind(ahead(2), FoodKind::Bonus),
+        ];
+        sim.refresh();
+        sim.step(&[]);
+        assert_eq!(sim.snake().points, 1);
+        sim.step(&[]);
+        assert_eq!(sim.snake().points, 1 + 2 * FoodKind::Bonus.points());
+
+        let (_, duration) = FoodKind::Multiplier.effect().unwrap();
+        sim.snakes[0].effects.advance(duration);
+        let next = GridPosition::new_from_move(sim.snake().head.pos, sim.snake().dir, sim.grid());
+        sim.foods = vec![Food::new(next)];
+        sim.refresh();
+        sim.step(&[]);
+        assert_eq!(sim.snake().points, 2 + 2 * FoodKind::Bonus.points());
+    }
+}
@@ -0,0 +1,436 @@
+//! Recording and playing back games.
+//!
+//! A replay holds everything needed to play a game again exactly as it
+//! happened: the seed the game was started with, the rules it was played
+//! under and every turn that the simulation accepted, together with the tick
+//! it was accepted on.
+//!
+//! Replays are stored as plain text, one record per line. Empty lines and
+//! lines starting with `#` are ignored:
+//!
+//! ```text
+//! # snake-remix replay
+//! version 1
+//! seed 12345
+//! grid 56 30
+//! boundary wrap
+//! turn 17 0 up
+//! turn 20 0 left
+//! end 1234 57
+//! ```
+//!
+//! * `version <n>` - format version, currently 1.
+//! * `seed <n>` - the seed passed to `Simulation::with_rules` by
+//!   `Replay::simulation`.
+//! * `grid <width> <height>` - size of the board.
+//! * `boundary <wrap|solid>` - optional, what happens at the edges of the
+//!   board. Defaults to `wrap`.
+//! * `level <name>` - optional, the name of the level that was played.
+//! * `goal <length|points> <n>` - optional, the goal of the level, in the
+//!   format of `src/level.rs`.
+//! * `players <n>` - optional, the number of snakes on the board. Defaults
+//!   to 1; 2 is a versus match.
+//! * `power-ups <on|off>` - optional, whether power-ups were switched on.
+//!   Defaults to `off`.
+//! * `food <policy>` - optional, how food was put on the board, in the format
+//!   of `src/spawn.rs`. Defaults to `fixed 1`.
+//! * `speed <curve>` - optional, the speed curve, in the format of
+//!   `src/difficulty.rs`. Defaults to the curve of the normal difficulty.
+//! * `mode <mode>` - optional, the game mode, one of those in `src/mode.rs`.
+//!   Defaults to `classic`.
+//! * `row <cells>` - optional, one row of the level's map in the format of
+//!   `src/level.rs`. When rows are given they replace the `grid` record;
+//!   without them the board is open.
+//! * `turn <tick> <snake> <dir>` - a turn of snake number `<snake>` towards
+//!   `up`, `down`, `left` or `right`, applied before tick `<tick>` was
+//!   played. Turns are listed in the order they were applied.
+//! * `end <tick> <points>` - optional, the tick the recording stopped on and
+//!   the points the first snake had by then. Used to verify a replay.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::difficulty::SpeedCurve;
+use crate::level::{Level, LevelError};
+use crate::mode::Mode;
+use crate::sim::{Direction, Event, GameStates, Grid, Input, Rules, Simulation};
+use crate::spawn::SpawnPolicy;
+
+/// The version written by `Replay::to_string`.
+pub const VERSION: u32 = 1;
+
+/// A turn accepted by the simulation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Turn {
+    pub tick: u64,
+    pub snake: usize,
+    pub dir: Direction,
+}
+
+/// How far a recorded game got.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub tick: u64,
+    pub points: i32,
+}
+
+/// A recorded game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    pub level: Level,
+    pub players: usize,
+    pub rules: Rules,
+    pub turns: Vec<Turn>,
+    pub end: Option<Outcome>,
+}
+
+/// Things that can go wrong while loading a replay.
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "{}", e),
+            ReplayError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+impl From<LevelError> for ReplayError {
+    fn from(e: LevelError) -> Self {
+        match e {
+            LevelError::Io(e) => ReplayError::Io(e),
+            LevelError::Parse { line, message } => ReplayError::Parse { line, message },
+        }
+    }
+}
+
+impl Replay {
+    /// Starts recording a game that was set up with `Simulation::new(level, seed)`.
+    pub fn new(seed: u64, level: Level) -> Self {
+        Replay {
+            seed,
+            level,
+            players: 1,
+            rules: Rules::default(),
+            turns: Vec::new(),
+            end: None,
+        }
+    }
+
+    /// Starts recording the game `sim` is about to play. Call this before the
+    /// first tick.
+    pub fn for_simulation(sim: &Simulation) -> Self {
+        Replay {
+            players: sim.snakes.len(),
+            rules: sim.rules,
+            ..Replay::new(sim.seed, sim.level.clone())
+        }
+    }
+
+    /// Remembers an event if it is one that has to be replayed. `tick` is the
+    /// simulation's tick at the time the event happened.
+    pub fn record(&mut self, tick: u64, event: &Event) {
+        if let Event::Turned { snake, dir } = *event {
+            self.turns.push(Turn { tick, snake, dir });
+        }
+    }
+
+    /// Notes how far the game got, so that playback can be verified.
+    pub fn finish(&mut self, sim: &Simulation) {
+        self.end = Some(Outcome {
+            tick: sim.tick,
+            points: sim.snake().points,
+        });
+    }
+
+    /// A fresh simulation in the state the recorded game started in.
+    pub fn simulation(&self) -> Simulation {
+        Simulation::with_rules(self.level.clone(), self.seed, self.players, self.rules)
+    }
+
+    /// Plays the whole replay headless and returns the simulation in the state
+    /// the recording ended in.
+    pub fn play(&self) -> Simulation {
+        let mut sim = self.simulation();
+        let mut playback = Playback::new(self);
+        let last_tick = self.end.map(|end| end.tick);
+        loop {
+            if sim.game_states != GameStates::GameOn || last_tick.is_some_and(|t| sim.tick >= t) {
+                break;
+            }
+            // Without an end record we stop once the inputs have run out.
+            if last_tick.is_none() && playback.is_done() {
+                break;
+            }
+            let inputs = playback.inputs(sim.tick);
+            sim.step(&inputs);
+        }
+        sim
+    }
+
+    /// Checks that playing the replay reproduces the recorded outcome.
+    pub fn verify(&self) -> Result<Outcome, String> {
+        let sim = self.play();
+        let got = Outcome {
+            tick: sim.tick,
+            points: sim.snake().points,
+        };
+        match self.end {
+            Some(expected) if expected != got => Err(format!(
+                "expected the game to end on tick {} with {} points, but it ended on tick {} with {} points",
+                expected.tick, expected.points, got.tick, got.points
+            )),
+            _ => Ok(got),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        let text = fs::read_to_string(path)?;
+        Replay::parse(&text)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+        let mut replay = Replay::new(0, Level::open(Grid::default()));
+        let mut seen_seed = false;
+        let mut grid = Grid::default();
+        let mut name = None;
+        let mut goal = None;
+        let mut rows = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| ReplayError::Parse {
+                line: i + 1,
+                message,
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let field = |n: usize| fields.get(n).cloned().ok_or_else(|| error(format!("missing field {}", n)));
+            let number = |n: usize| -> Result<u64, ReplayError> {
+                field(n)?.parse().map_err(|e| error(format!("{}", e)))
+            };
+
+            match fields[0] {
+                "version" => {
+                    let version = number(1)?;
+                    if version != u64::from(VERSION) {
+                        return Err(error(format!("unsupported version {}", version)));
+                    }
+                }
+                "seed" => {
+                    replay.seed = number(1)?;
+                    seen_seed = true;
+                }
+                "grid" => {
+                    let sized = Grid::sized(number(1)?, number(2)?).map_err(error)?;
+                    grid.width = sized.width;
+                    grid.height = sized.height;
+                }
+                "boundary" => grid.boundary = field(1)?.parse().map_err(error)?,
+                "level" => name = Some(line["level".len()..].trim().to_string()),
+                "goal" => goal = Some(line["goal".len()..].trim().parse().map_err(error)?),
+                "players" => {
+                    replay.players = number(1)? as usize;
+                    if replay.players == 0 || replay.players > 2 {
+                        return Err(error("a game has one or two players".to_string()));
+                    }
+                }
+                "power-ups" => {
+                    replay.rules.power_ups = match field(1)? {
+                        "on" => true,
+                        "off" => false,
+                        other => return Err(error(format!("expected `on` or `off`, found `{}`", other))),
+                    }
+                }
+                "mode" => replay.rules.mode = field(1)?.parse().map_err(error)?,
+                "speed" => replay.rules.speed = line["speed".len()..].trim().parse().map_err(error)?,
+                "food" => replay.rules.food = line["food".len()..].trim().parse().map_err(error)?,
+                "row" => rows.push((i + 1, field(1)?)),
+                "turn" => replay.turns.push(Turn {
+                    tick: number(1)?,
+                    snake: number(2)? as usize,
+                    dir: field(3)?.parse().map_err(error)?,
+                }),
+                "end" => {
+                    replay.end = Some(Outcome {
+                        tick: number(1)?,
+                        points: field(2)?.parse().map_err(|e| error(format!("{}", e)))?,
+                    })
+                }
+                other => return Err(error(format!("unknown record `{}`", other))),
+            }
+        }
+
+        if !seen_seed {
+            return Err(ReplayError::Parse {
+                line: 0,
+                message: "no seed given".to_string(),
+            });
+        }
+
+        replay.level = if rows.is_empty() {
+            Level::open(grid)
+        } else {
+            Level::from_rows(&rows, grid.boundary)?
+        };
+        if let Some(name) = name {
+            replay.level.name = name;
+        }
+        replay.level.goal = goal;
+        Ok(replay)
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# snake-remix replay")?;
+        writeln!(f, "version {}", VERSION)?;
+        writeln!(f, "seed {}", self.seed)?;
+        let grid = &self.level.grid;
+        writeln!(f, "grid {} {}", grid.width, grid.height)?;
+        writeln!(f, "boundary {}", grid.boundary)?;
+        writeln!(f, "level {}", self.level.name)?;
+        if let Some(goal) = self.level.goal {
+            writeln!(f, "goal {}", goal)?;
+        }
+        if self.players != 1 {
+            writeln!(f, "players {}", self.players)?;
+        }
+        if self.rules.power_ups {
+            writeln!(f, "power-ups on")?;
+        }
+        if self.rules.food != SpawnPolicy::default() {
+            writeln!(f, "food {}", self.rules.food)?;
+        }
+        if self.rules.speed != SpeedCurve::default() {
+            writeln!(f, "speed {}", self.rules.speed)?;
+        }
+        if self.rules.mode != Mode::default() {
+            writeln!(f, "mode {}", self.rules.mode)?;
+        }
+        if !self.level.is_open() {
+            for row in self.level.rows() {
+                writeln!(f, "row {}", row)?;
+            }
+        }
+        for turn in &self.turns {
+            writeln!(f, "turn {} {} {}", turn.tick, turn.snake, turn.dir)?;
+        }
+        if let Some(end) = self.end {
+            writeln!(f, "end {} {}", end.tick, end.points)?;
+        }
+        Ok(())
+    }
+}
+
+/// Feeds the turns of a replay back into a simulation, tick by tick.
+pub struct Playback {
+    turns: Vec<Turn>,
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(replay: &Replay) -> Self {
+        Playback {
+            turns: replay.turns.clone(),
+            next: 0,
+        }
+    }
+
+    /// The inputs to pass to `Simulation::step` when the simulation is at `tick`.
+    pub fn inputs(&mut self, tick: u64) -> Vec<Input> {
+        let mut inputs = Vec::new();
+        while let Some(turn) = self.turns.get(self.next) {
+            if turn.tick > tick {
+                break;
+            }
+            inputs.push(Input::Turn {
+                snake: turn.snake,
+                dir: turn.dir,
+            });
+            self.next += 1;
+        }
+        inputs
+    }
+
+    /// Whether all recorded turns have been handed out.
+    pub fn is_done(&self) -> bool {
+        self.next >= self.turns.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::level::Goal;
+
+    #[test]
+    fn text_round_trip() {
+        let level = Level::parse("name Box\nboundary solid\ngoal length 10\nmap\n......\n.>..#.\n.*....\n....<.\n").unwrap();
+        let replay = Replay {
+            seed: 12345,
+            level,
+            players: 2,
+            rules: Rules {
+                power_ups: true,
+                food: SpawnPolicy::AfterEat(Duration::from_millis(1500)),
+                speed: "capped 120 5 10 min 40".parse().unwrap(),
+                mode: Mode::Survival,
+            },
+            turns: vec![
+                Turn {
+                    tick: 3,
+                    snake: 0,
+                    dir: Direction::Down,
+                },
+                Turn {
+                    tick: 3,
+                    snake: 1,
+                    dir: Direction::Up,
+                },
+            ],
+            end: Some(Outcome { tick: 9, points: 2 }),
+        };
+        let parsed = Replay::parse(&replay.to_string()).unwrap();
+        assert_eq!(parsed, replay);
+        assert_eq!(parsed.level.goal, Some(Goal::Length(10)));
+    }
+
+    #[test]
+    fn recorded_game_plays_back_the_same() {
+        let replay = Replay::parse(include_str!("../tests/data/bfs-12x8.replay")).unwrap();
+        assert_eq!(replay.verify(), Ok(Outcome { tick: 231, points: 42 }));
+    }
+
+    #[test]
+    fn board_sizes_and_points_that_do_not_fit_are_rejected() {
+        assert!(Replay::parse("version 1\nseed 1\ngrid 0 0\n").is_err());
+        assert!(Replay::parse("version 1\nseed 1\ngrid 70000 30\n").is_err());
+        assert!(Replay::parse("version 1\nseed 1\nend 10 3000000000\n").is_err());
+    }
+}
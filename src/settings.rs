@@ -0,0 +1,238 @@
+//! Player preferences that are kept between launches.
+//!
+//! Settings are stored as TOML in the user's config directory, e.g.
+//! `~/.config/snake-remix/settings.toml` on Linux. Every entry is optional;
+//! missing entries get their default value, and a file that cannot be read at
+//! all is ignored in favour of the defaults:
+//!
+//! ```toml
+//! music_on = true
+//! music_volume = 0.2     # 0 to 1
+//! sound_volume = 2.0     # 0 to 4
+//! speed = 1.0            # 0.1 to 16 times the normal speed
+//! display = "windowed"   # or "borderless", "fullscreen"
+//! window_width = 1280
+//! window_height = 720
+//! board_width = 56
+//! board_height = 30
+//! walls = false
+//! power_ups = true
+//! food = "fixed 1"       # how food turns up, see src/spawn.rs
+//! difficulty = "normal"  # or "easy", "hard", "insane"
+//! # speed_curve = "sqrt 100 8 min 30"   # replaces the difficulty, see src/difficulty.rs
+//! mode = "classic"       # or "time-attack", "survival", "endless"
+//! demo_after = 30        # seconds, 0 for no demo
+//! bot = "bfs"            # or "greedy", "random", "hamiltonian"
+//! gamepad_deadzone = 0.3
+//!
+//! [keys]
+//! turn_up = ["Up", "W"]
+//! pause = ["P", "Space"]
+//! ```
+//!
+//! Keys are given by their ggez `KeyCode` names, and each action may list
+//! several keys; see `src/controls.rs` for the actions.
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bot::Bot;
+use crate::controls::{Bindings, DEFAULT_DEADZONE};
+use crate::difficulty::{Difficulty, SpeedCurve};
+use crate::mode::Mode;
+use crate::sim::{Boundary, Grid, Rules};
+use crate::spawn::SpawnPolicy;
+use crate::store::{self, StoreError};
+
+/// Board sizes to choose from in the options. Boards set in the file are kept
+/// between the smallest and the largest of them.
+pub const BOARD_SIZES: [(i16, i16); 5] = [(28, 15), (42, 22), (56, 30), (70, 38), (84, 45)];
+
+/// The slowest and the fastest the game can run, compared to normal speed.
+pub const SPEED_RANGE: (f64, f64) = (0.1, 16.0);
+
+/// The loudest the music can be set, 1 being the volume it was recorded at.
+pub const MAX_MUSIC_VOLUME: f32 = 1.0;
+
+/// The loudest the sound effects can be set, 1 being the volume they were
+/// recorded at.
+pub const MAX_SOUND_VOLUME: f32 = 4.0;
+
+/// How the game window is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayMode {
+    /// A normal window with a title bar that can be resized.
+    Windowed,
+    /// A window without decorations covering the whole monitor.
+    Borderless,
+    /// Exclusive fullscreen.
+    Fullscreen,
+}
+
+impl FromStr for DisplayMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "windowed" => Ok(DisplayMode::Windowed),
+            "borderless" => Ok(DisplayMode::Borderless),
+            "fullscreen" => Ok(DisplayMode::Fullscreen),
+            other => Err(format!("unknown display mode `{}`", other)),
+        }
+    }
+}
+
+/// Everything the player can set up once and expect to stay that way.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub music_on: bool,
+    pub music_volume: f32,
+    /// Volume of the sound effects.
+    pub sound_volume: f32,
+    /// How fast the game runs compared to normal speed.
+    pub speed: f64,
+    pub display: DisplayMode,
+    pub window_width: u32,
+    pub window_height: u32,
+    pub board_width: i16,
+    pub board_height: i16,
+    /// Whether the board is surrounded by solid walls instead of wrapping around.
+    pub walls: bool,
+    /// Whether food other than plain food turns up.
+    pub power_ups: bool,
+    /// How food is put on the board.
+    pub food: SpawnPolicy,
+    pub difficulty: Difficulty,
+    /// A speed curve of the player's own, used instead of the difficulty's.
+    pub speed_curve: Option<SpeedCurve>,
+    pub mode: Mode,
+    /// Seconds without input on the title or game over screen before the demo
+    /// starts; 0 turns the demo off.
+    pub demo_after: u32,
+    /// The bot played against in a versus match against the computer.
+    pub bot: Bot,
+    /// How far a gamepad stick has to be pushed before it counts, from 0 to 1.
+    pub gamepad_deadzone: f32,
+    /// The keys bound to every action.
+    pub keys: Bindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let grid = Grid::default();
+        Settings {
+            music_on: true,
+            music_volume: 0.2,
+            sound_volume: 2.0,
+            speed: 1.0,
+            display: DisplayMode::Fullscreen,
+            window_width: 1920,
+            window_height: 1080,
+            board_width: grid.width,
+            board_height: grid.height,
+            walls: false,
+            power_ups: true,
+            food: SpawnPolicy::default(),
+            difficulty: Difficulty::default(),
+            speed_curve: None,
+            mode: Mode::default(),
+            demo_after: 30,
+            bot: Bot::Bfs,
+            gamepad_deadzone: DEFAULT_DEADZONE,
+            keys: Bindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Where the settings are kept, if the platform has a config directory.
+    pub fn path() -> Option<PathBuf> {
+        crate::project_dirs().map(|dirs| dirs.config_dir().join("settings.toml"))
+    }
+
+    /// Loads the settings at `path`, falling back to the defaults when there are
+    /// none yet or they cannot be read.
+    pub fn load_or_default(path: Option<&Path>) -> Settings {
+        store::load_or_default::<Settings>(path, "settings").sanitized()
+    }
+
+    /// Writes the settings to `path`, creating its directory if needed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), StoreError> {
+        store::save(path, self)
+    }
+
+    /// Reads settings from TOML text, the way they are loaded from a file.
+    pub fn parse(text: &str) -> Result<Settings, StoreError> {
+        let settings: Settings = toml::from_str(text).map_err(StoreError::Parse)?;
+        Ok(settings.sanitized())
+    }
+
+    /// Replaces values the game cannot work with by their defaults, and keeps
+    /// the volumes below their maximum, the speed within `SPEED_RANGE` and the
+    /// board within the sizes offered in the options.
+    fn sanitized(mut self) -> Settings {
+        let default = Settings::default();
+        if self.music_volume.is_nan() || self.music_volume < 0.0 {
+            self.music_volume = default.music_volume;
+        }
+        if self.sound_volume.is_nan() || self.sound_volume < 0.0 {
+            self.sound_volume = default.sound_volume;
+        }
+        self.music_volume = self.music_volume.min(MAX_MUSIC_VOLUME);
+        self.sound_volume = self.sound_volume.min(MAX_SOUND_VOLUME);
+        if !self.speed.is_finite() || self.speed <= 0.0 {
+            self.speed = default.speed;
+        }
+        self.speed = self.speed.clamp(SPEED_RANGE.0, SPEED_RANGE.1);
+        if self.window_width == 0 || self.window_height == 0 {
+            self.window_width = default.window_width;
+            self.window_height = default.window_height;
+        }
+        if self.gamepad_deadzone.is_nan() || self.gamepad_deadzone < 0.0 || self.gamepad_deadzone >= 1.0 {
+            self.gamepad_deadzone = default.gamepad_deadzone;
+        }
+        let (smallest, largest) = (BOARD_SIZES[0], BOARD_SIZES[BOARD_SIZES.len() - 1]);
+        self.board_width = self.board_width.clamp(smallest.0, largest.0);
+        self.board_height = self.board_height.clamp(smallest.1, largest.1);
+        self
+    }
+
+    /// The rules these settings ask for.
+    pub fn rules(&self) -> Rules {
+        Rules {
+            power_ups: self.power_ups,
+            food: self.food,
+            speed: self.speed_curve.unwrap_or_else(|| self.difficulty.speed()),
+            mode: self.mode,
+        }
+    }
+
+    /// The board these settings ask for.
+    pub fn grid(&self) -> Grid {
+        Grid {
+            boundary: if self.walls { Boundary::Solid } else { Boundary::Wrap },
+            ..Grid::new(self.board_width, self.board_height)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_the_game_cannot_use_are_replaced() {
+        let settings = Settings::parse("speed = -1.0\nboard_width = 1\nboard_height = 5000\n").unwrap();
+        assert_eq!(settings.speed, Settings::default().speed);
+        assert_eq!(Settings::parse("speed = 1e300\n").unwrap().speed, SPEED_RANGE.1);
+        let loud = Settings::parse("music_volume = 2.5\nsound_volume = 1e9\n").unwrap();
+        assert_eq!(loud.music_volume, MAX_MUSIC_VOLUME);
+        assert_eq!(loud.sound_volume, MAX_SOUND_VOLUME);
+        assert_eq!(settings.board_width, BOARD_SIZES[0].0);
+        assert_eq!(settings.board_height, BOARD_SIZES[BOARD_SIZES.len() - 1].1);
+    }
+}
@@ -0,0 +1,1348 @@
+//! The renderer-independent part of the game: the grid, the snakes, the food
+//! and the rules that tie them together. Nothing in here knows about ggez, so
+//! the whole game can be advanced headless by calling `Simulation::step`.
+
+use std::convert::TryFrom;
+use std::collections::LinkedList;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+// Bring the `Rng` trait into scope so that we can generate some random numbers.
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use crate::difficulty::SpeedCurve;
+use crate::level::{Level, Start};
+use crate::mode::Mode;
+use crate::powerup::{Effect, Effects, FoodKind, SHRINK_BY};
+use crate::spawn::SpawnPolicy;
+
+/// The random number generator owned by a `Simulation`. Pcg32 produces the same
+/// stream for the same seed on every platform, which makes games reproducible.
+pub type GameRng = Pcg32;
+
+/// The states the game can be in. The front-end draws overlays depending on
+/// which one is active, but only the simulation changes it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameStates {
+    GameOver,
+    GameOn,
+    Pause,
+    Restart,
+    /// The snake filled the whole board, there is nowhere left to put food,
+    /// reached the goal of the level or lived through a time attack.
+    Won,
+}
+
+/// What happens when a snake moves off the edge of the grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// The classic rule: come back in on the opposite side.
+    Wrap,
+    /// The grid is surrounded by walls and running into them is lethal.
+    Solid,
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Boundary::Wrap => "wrap",
+            Boundary::Solid => "solid",
+        })
+    }
+}
+
+impl FromStr for Boundary {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(Boundary::Wrap),
+            "solid" => Ok(Boundary::Solid),
+            other => Err(format!("unknown boundary `{}`", other)),
+        }
+    }
+}
+
+/// The size of our game board in terms of how many grid cells it takes up,
+/// and what happens at its edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grid {
+    pub width: i16,
+    pub height: i16,
+    pub boundary: Boundary,
+}
+
+impl Grid {
+    /// Boards are at least this many cells wide and high, so that two snakes
+    /// and their food fit on an open board.
+    pub const MIN_SIZE: i16 = 4;
+    /// Boards are at most this many cells wide and high.
+    pub const MAX_SIZE: i16 = 1000;
+
+    pub fn new(width: i16, height: i16) -> Self {
+        Grid {
+            width,
+            height,
+            boundary: Boundary::Wrap,
+        }
+    }
+
+    /// A wrapping grid of the given size, if both sides are between `MIN_SIZE`
+    /// and `MAX_SIZE`.
+    pub fn sized(width: u64, height: u64) -> Result<Grid, String> {
+        let side = |n: u64| match i16::try_from(n) {
+            Ok(n) if (Grid::MIN_SIZE..=Grid::MAX_SIZE).contains(&n) => Ok(n),
+            _ => Err(format!(
+                "a board side must be between {} and {}, found {}",
+                Grid::MIN_SIZE,
+                Grid::MAX_SIZE,
+                n
+            )),
+        };
+        Ok(Grid::new(side(width)?, side(height)?))
+    }
+
+    /// Whether `pos` lies on the grid.
+    pub fn contains(&self, pos: GridPosition) -> bool {
+        pos.x >= 0 && pos.x < self.width && pos.y >= 0 && pos.y < self.height
+    }
+}
+
+/// Reads a board size like `56x30`. The boundary is `wrap`.
+impl FromStr for Grid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("expected a size like `56x30`, found `{}`", s);
+        let mut parts = s.splitn(2, 'x');
+        let mut number = || -> Result<u64, String> {
+            match parts.next().map(str::parse) {
+                Some(Ok(n)) => Ok(n),
+                _ => Err(error()),
+            }
+        };
+        let width = number()?;
+        let height = number()?;
+        Grid::sized(width, height)
+    }
+}
+
+impl Default for Grid {
+    /// We choose to make a 56 x 30 game board by default.
+    fn default() -> Self {
+        Grid::new(56, 30)
+    }
+}
+
+/// Now we define a struct that will hold an entity's position on our game board
+/// or grid which we defined above. We'll use signed integers because we only want
+/// to store whole numbers, and we need them to be signed so that they work properly
+/// with our modulus arithmetic later.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GridPosition {
+    pub x: i16,
+    pub y: i16,
+}
+
+/// This is a trait that provides a modulus function that works for negative values
+/// rather than just the standard remainder op (%) which does not. We'll use this
+/// to get our snake to wrap from one side of the game board around to the other
+/// when it goes off the top, bottom, left, or right side of the screen.
+pub trait ModuloSigned {
+    fn modulo(&self, n: Self) -> Self;
+}
+
+/// Here we implement our `ModuloSigned` trait for any type T which implements
+/// `Add` (the `+` operator) with an output type T and Rem (the `%` operator)
+/// that also has an output type of T, and that can be cloned. These are the bounds
+/// that we need in order to implement a modulus function that works for negative numbers
+/// as well.
+impl<T> ModuloSigned for T
+where
+    T: std::ops::Add<Output = T> + std::ops::Rem<Output = T> + Clone,
+{
+    fn modulo(&self, n: T) -> T {
+        // Because of our trait bounds, we can now apply these operators.
+        (self.clone() % n.clone() + n.clone()) % n
+    }
+}
+
+impl GridPosition {
+    /// We make a standard helper function so that we can create a new `GridPosition`
+    /// more easily.
+    pub fn new(x: i16, y: i16) -> Self {
+        GridPosition { x, y }
+    }
+
+    /// As well as a helper function that will give us a random `GridPosition` from
+    /// `(0, 0)` to `(max_x, max_y)` using the given random number generator.
+    pub fn random<R: Rng>(rng: &mut R, max_x: i16, max_y: i16) -> Self {
+        // We can use `.into()` to convert from `(i16, i16)` to a `GridPosition` since
+        // we implement `From<(i16, i16)>` for `GridPosition` below.
+        (
+            rng.gen_range::<i16, i16, i16>(0, max_x),
+            rng.gen_range::<i16, i16, i16>(0, max_y),
+        )
+            .into()
+    }
+
+    /// We'll make another helper function that takes one grid position and returns a new one after
+    /// making one move in the direction of `dir`. On a wrapping grid we use our `SignedModulo` trait
+    /// above, which is now implemented on `i16` because it satisfies the trait bounds,
+    /// to automatically wrap around within our grid size if the move would have otherwise
+    /// moved us off the board to the top, bottom, left, or right. On a solid grid the
+    /// position is left off the board, where it runs into the wall.
+    pub fn new_from_move(pos: GridPosition, dir: Direction, grid: &Grid) -> Self {
+        let moved = match dir {
+            Direction::Up => GridPosition::new(pos.x, pos.y - 1),
+            Direction::Down => GridPosition::new(pos.x, pos.y + 1),
+            Direction::Left => GridPosition::new(pos.x - 1, pos.y),
+            Direction::Right => GridPosition::new(pos.x + 1, pos.y),
+        };
+        match grid.boundary {
+            Boundary::Wrap => {
+                GridPosition::new(moved.x.modulo(grid.width), moved.y.modulo(grid.height))
+            }
+            Boundary::Solid => moved,
+        }
+    }
+}
+
+/// Positions are shown to players as column and row, counting from 1.
+impl fmt::Display for GridPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}, row {}", self.x + 1, self.y + 1)
+    }
+}
+
+/// And here we implement `From` again to allow us to easily convert between
+/// `(i16, i16)` and a `GridPosition`.
+impl From<(i16, i16)> for GridPosition {
+    fn from(pos: (i16, i16)) -> Self {
+        GridPosition { x: pos.0, y: pos.1 }
+    }
+}
+
+/// Next we create an enum that will represent all the possible
+/// directions that our snake could move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// We create a helper function that will allow us to easily get the inverse
+    /// of a `Direction` which we can use later to check if the player should be
+    /// able to move the snake in a certain direction.
+    pub fn inverse(&self) -> Self {
+        match *self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "up" => Ok(Direction::Up),
+            "down" => Ok(Direction::Down),
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            other => Err(format!("unknown direction `{}`", other)),
+        }
+    }
+}
+
+/// This is mostly just a semantic abstraction over a `GridPosition` to represent
+/// a segment of the snake. It could be useful to, say, have each segment contain its
+/// own color or something similar. This is an exercise left up to the reader ;)
+#[derive(Clone, Copy, Debug)]
+pub struct Segment {
+    pub pos: GridPosition,
+}
+
+impl Segment {
+    pub fn new(pos: GridPosition) -> Self {
+        Segment { pos }
+    }
+}
+
+/// This is again an abstraction over a `GridPosition` that represents
+/// a piece of food the snake can eat.
+#[derive(Clone, Copy, Debug)]
+pub struct Food {
+    pub pos: GridPosition,
+    pub kind: FoodKind,
+    /// How much longer it stays on the board, if it does not stay until eaten.
+    pub left: Option<Duration>,
+}
+
+impl Food {
+    pub fn new(pos: GridPosition) -> Self {
+        Food::with_kind(pos, FoodKind::Plain)
+    }
+
+    pub fn with_kind(pos: GridPosition, kind: FoodKind) -> Self {
+        Food {
+            pos,
+            kind,
+            left: kind.lifetime(),
+        }
+    }
+}
+
+/// Here we define an enum of the possible things that the snake could have "eaten"
+/// during an update of the game. It could have either eaten a piece of `Food`,
+/// it could have eaten `Itself` if the head ran into its body, a `Wall` if the
+/// head ran into a wall of the level or off a solid grid, or another `Snake`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ate {
+    Itself,
+    Food,
+    Wall,
+    Snake,
+}
+
+impl Ate {
+    /// Whether running into this ends the snake's game.
+    pub fn is_lethal(self) -> bool {
+        self != Ate::Food
+    }
+
+    /// What the snake died of if it ran into this, or `None` for food.
+    pub fn cause(self) -> Option<Cause> {
+        match self {
+            Ate::Food => None,
+            Ate::Itself => Some(Cause::Itself),
+            Ate::Wall => Some(Cause::Wall),
+            Ate::Snake => Some(Cause::Opponent),
+        }
+    }
+}
+
+/// Why a snake died.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cause {
+    /// Its head ran into its own body.
+    Itself,
+    /// It ran into a wall of the level or off a solid grid.
+    Wall,
+    /// It ran into another snake.
+    Opponent,
+    /// The game lasted `Simulation::max_ticks` ticks.
+    Timeout,
+    /// It went too long without eating, see `Mode::starvation`.
+    Starved,
+}
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Cause::Itself => "itself",
+            Cause::Wall => "wall",
+            Cause::Opponent => "opponent",
+            Cause::Timeout => "timeout",
+            Cause::Starved => "starved",
+        })
+    }
+}
+
+/// Now we make a struct that contains all the information needed to describe the
+/// state of the Snake itself.
+pub struct Snake {
+    /// First we have the head of the snake, which is a single `Segment`.
+    pub head: Segment,
+    /// Then we have the current direction the snake is moving. This is
+    /// the direction it will move when `update` is called on it.
+    pub dir: Direction,
+    /// Next we have the body, which we choose to represent as a `LinkedList`
+    /// of `Segment`s.
+    pub body: LinkedList<Segment>,
+    /// Now we have a property that represents the result of the last update
+    /// that was performed. The snake could have eaten nothing (None), Food (Some(Ate::Food)),
+    /// Itself (Some(Ate::Itself)) or a Wall (Some(Ate::Wall))
+    pub ate: Option<Ate>,
+    /// Finally we store the direction that the snake was traveling the last
+    /// time that `update` was called, which we will use to determine valid
+    /// directions that it could move the next time update is called.
+    pub last_update_dir: Direction,
+    /// Store the direction that will be used in the `update` after the next `update`
+    /// This is needed so a user can press two directions (eg. left then up)
+    /// before one `update` has happened. It sort of queues up key press input
+    pub next_dir: Option<Direction>,
+
+    pub points: i32,
+    /// Pieces of food eaten, whatever they were worth.
+    pub eaten: u32,
+    /// Why the snake died, once it has.
+    pub died: Option<Cause>,
+    /// The power-ups the snake is under.
+    pub effects: Effects,
+    /// Game time since the snake last ate.
+    pub hunger: Duration,
+}
+
+impl Snake {
+    pub fn new(start: Start, grid: &Grid) -> Self {
+        let mut body = LinkedList::new();
+        // Our snake will initially have a head and one body segment right behind it,
+        // and will be moving in the direction the level tells us.
+        body.push_back(Segment::new(start.tail(grid)));
+
+        Snake {
+            head: Segment::new(start.pos),
+            dir: start.dir,
+            last_update_dir: start.dir,
+            body,
+            ate: None,
+            next_dir: None,
+            points: 0,
+            eaten: 0,
+            died: None,
+            effects: Effects::default(),
+            hunger: Duration::from_millis(0),
+        }
+    }
+
+    /// Number of grid cells the snake occupies, head included.
+    pub fn length(&self) -> usize {
+        self.body.len() + 1
+    }
+
+    /// A helper function that determines whether
+    /// the snake eats any of the pieces of Food on the board based
+    /// on its current position
+    fn eats(&self, foods: &[Food]) -> bool {
+        foods.iter().any(|food| self.head.pos == food.pos)
+    }
+
+    /// A helper function that determines whether
+    /// the snake eats itself based on its current position. A ghost passes
+    /// right through its own body.
+    fn eats_self(&self) -> bool {
+        !self.effects.is_active(Effect::Ghost) && self.body.iter().any(|seg| self.head.pos == seg.pos)
+    }
+
+    /// Takes up to `segments` segments off the tail, always leaving one behind the head.
+    fn shrink(&mut self, segments: usize, occupancy: &mut Occupancy) {
+        for _ in 0..segments.min(self.body.len() - 1) {
+            if let Some(seg) = self.body.pop_back() {
+                occupancy.leave(seg.pos);
+            }
+        }
+    }
+
+    /// Eats a piece of food the head just moved onto.
+    fn eat(&mut self, food: &Food, occupancy: &mut Occupancy) {
+        let multiplier = if self.effects.is_active(Effect::Multiplier) { 2 } else { 1 };
+        self.points = self.points.saturating_add(food.kind.points().saturating_mul(multiplier));
+        self.eaten += 1;
+        self.hunger = Duration::from_millis(0);
+        if food.kind == FoodKind::Shrink {
+            self.shrink(SHRINK_BY, occupancy);
+        }
+        if let Some((effect, duration)) = food.kind.effect() {
+            self.effects.add(effect, duration);
+        }
+    }
+
+    /// Queue up a turn requested by the player. Returns whether the turn was
+    /// accepted; turning straight back into the body never is.
+    pub fn turn(&mut self, dir: Direction) -> bool {
+        // We check if a new direction has already been set
+        // and make sure the new direction is different then `dir`
+        if self.dir != self.last_update_dir && dir.inverse() != self.dir {
+            self.next_dir = Some(dir);
+            true
+        } else if dir.inverse() != self.last_update_dir {
+            // If no new direction has been set and the direction is not the inverse
+            // of the `last_update_dir`, then set the snake's new direction to be the
+            // direction the user pressed.
+            self.dir = dir;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The main update function for our snake which gets called every time
+    /// we want to update the game state.
+    pub fn update(&mut self, foods: &[Food], level: &Level) {
+        // If `last_update_dir` has already been updated to be the same as `dir`
+        // and we have a `next_dir`, then set `dir` to `next_dir` and unset `next_dir`
+        if self.last_update_dir == self.dir {
+            if let Some(next_dir) = self.next_dir.take() {
+                self.dir = next_dir;
+            }
+        }
+        // First we get a new head position by using our `new_from_move` helper
+        // function from earlier. We move our head in the direction we are currently
+        // heading.
+        let new_head_pos = GridPosition::new_from_move(self.head.pos, self.dir, &level.grid);
+        // Next we create a new segment will be our new head segment using the
+        // new position we just made.
+        let new_head = Segment::new(new_head_pos);
+        // Then we push our current head Segment onto the front of our body
+        self.body.push_front(self.head);
+        // And finally make our actual head the new Segment we created. This has
+        // effectively moved the snake in the current direction.
+        self.head = new_head;
+        // Next we check whether the snake hits a wall, eats itself or some food, and if so,
+        // we set our `ate` member to reflect that state.
+        if !level.is_passable(self.head.pos) {
+            self.ate = Some(Ate::Wall);
+        } else if self.eats_self() {
+            self.ate = Some(Ate::Itself);
+        } else if self.eats(foods) {
+            self.ate = Some(Ate::Food);
+        } else {
+            self.ate = None
+        }
+        // If we didn't eat anything this turn, we remove the last segment from our body,
+        // which gives the illusion that the snake is moving. In reality, all the segments stay
+        // stationary, we just add a segment to the front and remove one from the back. If we eat
+        // a piece of food, then we leave the last segment so that we extend our body by one.
+        if self.ate.is_none() {
+            self.body.pop_back();
+        }
+        // And set our last_update_dir to the direction we just moved.
+        self.last_update_dir = self.dir;
+    }
+}
+
+/// Everything a front-end, bot or test can ask the simulation to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// Steer snake number `snake` towards `dir`.
+    Turn { snake: usize, dir: Direction },
+    /// Toggle between `GameOn` and `Pause`.
+    Pause,
+    /// Start a new game once the current one is over.
+    Restart,
+}
+
+/// What happened while applying inputs or advancing the simulation. The
+/// front-end reacts to these (sounds, text), the simulation itself does not
+/// need them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A turn was accepted for the given snake.
+    Turned { snake: usize, dir: Direction },
+    /// The given snake ate something during the last tick.
+    Ate { snake: usize, ate: Ate },
+    /// The given snake died during the last tick.
+    Died { snake: usize, cause: Cause },
+    /// The game moved from one state to another.
+    StateChanged { from: GameStates, to: GameStates },
+}
+
+/// The rules a game is played under, on top of those of its level. Replays
+/// record them, so that a game plays back under the rules it was played with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rules {
+    /// Whether food other than plain food turns up, see `crate::powerup`.
+    pub power_ups: bool,
+    /// How food is put on the board, see `crate::spawn`.
+    pub food: SpawnPolicy,
+    /// How fast the game runs, see `crate::difficulty`.
+    pub speed: SpeedCurve,
+    /// How the game is won or lost, see `crate::mode`.
+    pub mode: Mode,
+}
+
+/// Rounds won by every player of a versus match, kept from one round to the next.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scoreboard {
+    pub wins: Vec<u32>,
+    /// Rounds in which both snakes died at once.
+    pub draws: u32,
+}
+
+impl Scoreboard {
+    pub fn new(players: usize) -> Self {
+        Scoreboard {
+            wins: vec![0; players],
+            draws: 0,
+        }
+    }
+
+    /// Number of rounds played so far.
+    pub fn rounds(&self) -> u32 {
+        self.wins.iter().sum::<u32>() + self.draws
+    }
+}
+
+/// How far from the first piece of a cluster of food the others may turn up, in moves.
+const CLUSTER_RADIUS: i16 = 2;
+
+/// What is on every cell of the board. The simulation keeps it up to date as
+/// the snakes move and food comes and goes, so that neither spawning food nor
+/// the bots have to go through every segment of every snake.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Occupancy {
+    grid: Grid,
+    /// The number of walls and snake segments on every cell, row by row. Snakes
+    /// can overlap, e.g. when two heads meet.
+    solid: Vec<u32>,
+    /// Whether there is food on every cell, row by row.
+    food: Vec<bool>,
+    /// Cells without a wall or a snake.
+    open: usize,
+    /// Cells without a wall, a snake or food.
+    free: usize,
+}
+
+impl Occupancy {
+    /// Marks the walls of `level`, the snakes and the food.
+    fn new(level: &Level, snakes: &[Snake], foods: &[Food]) -> Self {
+        let grid = level.grid;
+        let cells = grid.width as usize * grid.height as usize;
+        let mut occupancy = Occupancy {
+            grid,
+            solid: vec![0; cells],
+            food: vec![false; cells],
+            open: cells,
+            free: cells,
+        };
+        for wall in level.walls() {
+            occupancy.enter(wall);
+        }
+        for snake in snakes {
+            for seg in std::iter::once(&snake.head).chain(snake.body.iter()) {
+                occupancy.enter(seg.pos);
+            }
+        }
+        for food in foods {
+            occupancy.set_food(food.pos, true);
+        }
+        occupancy
+    }
+
+    fn index(&self, pos: GridPosition) -> usize {
+        pos.y as usize * self.grid.width as usize + pos.x as usize
+    }
+
+    /// Whether moving onto `pos` is lethal: it is off the board, a wall or part
+    /// of a snake.
+    pub fn is_blocked(&self, pos: GridPosition) -> bool {
+        !self.grid.contains(pos) || self.solid[self.index(pos)] > 0
+    }
+
+    /// Whether nothing at all is on `pos`, not even food.
+    pub fn is_free(&self, pos: GridPosition) -> bool {
+        !self.is_blocked(pos) && !self.food[self.index(pos)]
+    }
+
+    /// Whether there is food on `pos`.
+    pub fn has_food(&self, pos: GridPosition) -> bool {
+        self.grid.contains(pos) && self.food[self.index(pos)]
+    }
+
+    /// Number of cells without a wall, a snake or food.
+    pub fn free(&self) -> usize {
+        self.free
+    }
+
+    /// Whether the snakes fill every cell that is not a wall.
+    pub fn is_full(&self) -> bool {
+        self.open == 0
+    }
+
+    /// The `n`-th cell without a wall, a snake or food, counting row by row.
+    fn nth_free(&self, n: usize) -> Option<GridPosition> {
+        let i = (0..self.solid.len())
+            .filter(|&i| self.solid[i] == 0 && !self.food[i])
+            .nth(n)?;
+        let width = self.grid.width as usize;
+        Some(GridPosition::new((i % width) as i16, (i / width) as i16))
+    }
+
+    /// A wall or a snake segment moved onto `pos`.
+    fn enter(&mut self, pos: GridPosition) {
+        if !self.grid.contains(pos) {
+            return;
+        }
+        let i = self.index(pos);
+        if self.solid[i] == 0 {
+            self.open -= 1;
+            if !self.food[i] {
+                self.free -= 1;
+            }
+        }
+        self.solid[i] += 1;
+    }
+
+    /// A snake segment left `pos`.
+    fn leave(&mut self, pos: GridPosition) {
+        if !self.grid.contains(pos) {
+            return;
+        }
+        let i = self.index(pos);
+        self.solid[i] -= 1;
+        if self.solid[i] == 0 {
+            self.open += 1;
+            if !self.food[i] {
+                self.free += 1;
+            }
+        }
+    }
+
+    /// A piece of food was put on `pos` or taken off it.
+    fn set_food(&mut self, pos: GridPosition, food: bool) {
+        let i = self.index(pos);
+        if self.food[i] == food {
+            return;
+        }
+        self.food[i] = food;
+        if self.solid[i] == 0 {
+            if food {
+                self.free -= 1;
+            } else {
+                self.free += 1;
+            }
+        }
+    }
+}
+
+/// The whole game world: the level, the snakes living on it, the food and the
+/// state the game is in.
+///
+/// All randomness comes from `rng`, which is seeded with `seed`. A given seed
+/// plus the same sequence of inputs always plays out exactly the same game.
+pub struct Simulation {
+    pub level: Level,
+    pub snakes: Vec<Snake>,
+    /// The pieces of food on the board, oldest first.
+    pub foods: Vec<Food>,
+    pub game_states: GameStates,
+    /// The seed the current game was started with.
+    pub seed: u64,
+    /// Number of ticks the current game has been running, not counting pauses.
+    pub tick: u64,
+    /// How long the current game has been running on screen: the sum of the
+    /// intervals of all ticks so far.
+    pub game_time: Duration,
+    /// If set, the game ends after this many ticks and the snakes still alive
+    /// die of `Cause::Timeout`.
+    pub max_ticks: Option<u64>,
+    /// With more than one snake, the game is a versus match played in rounds.
+    pub scoreboard: Scoreboard,
+    /// The snake that won the round that just ended, if it was not a draw.
+    pub winner: Option<usize>,
+    pub rules: Rules,
+    /// What is on every cell. Call `Simulation::refresh` after changing the
+    /// snakes or the food by hand.
+    occupancy: Occupancy,
+    /// The game times at which pieces of food eaten under
+    /// `SpawnPolicy::AfterEat` are replaced.
+    pending: Vec<Duration>,
+    /// The game time at which the next piece turns up under `SpawnPolicy::Timed`.
+    next_timed: Duration,
+    rng: GameRng,
+}
+
+impl Simulation {
+    /// Sets up a fresh game on the given level.
+    pub fn new(level: Level, seed: u64) -> Self {
+        Simulation::with_players(level, seed, 1)
+    }
+
+    /// Sets up a fresh game with one snake per player. With two players it is a
+    /// versus match: the round is over as soon as a snake dies.
+    pub fn with_players(level: Level, seed: u64, players: usize) -> Self {
+        Simulation::with_rules(level, seed, players, Rules::default())
+    }
+
+    /// Sets up a fresh game with one snake per player, played under `rules`.
+    ///
+    /// Panics if there is no room for food, which cannot happen on levels that
+    /// pass `Level::check` with one or two players.
+    pub fn with_rules(level: Level, seed: u64, players: usize, rules: Rules) -> Self {
+        let rng = GameRng::seed_from_u64(seed);
+        let snakes: Vec<Snake> = level
+            .starts_for(players)
+            .into_iter()
+            .map(|start| Snake::new(start, &level.grid))
+            .collect();
+        let next_timed = match rules.food {
+            SpawnPolicy::Timed { every, .. } => every,
+            _ => Duration::from_millis(0),
+        };
+
+        let mut sim = Simulation {
+            level,
+            snakes,
+            foods: Vec::new(),
+            game_states: GameStates::GameOn,
+            seed,
+            tick: 0,
+            game_time: Duration::from_millis(0),
+            max_ticks: None,
+            scoreboard: Scoreboard::new(players),
+            winner: None,
+            rules,
+            occupancy: Occupancy::default(),
+            pending: Vec::new(),
+            next_timed,
+            rng,
+        };
+        sim.refresh();
+        // Then we choose random free places to put the first pieces of food,
+        // close together if the food comes in clusters.
+        let first = sim.random_free_cell(None).expect("the level was not checked for room");
+        sim.add_food(sim.new_food(first, FoodKind::Plain));
+        let near = match rules.food {
+            SpawnPolicy::Clustered(_) => Some(first),
+            _ => None,
+        };
+        for _ in 1..rules.food.initial() {
+            match sim.random_free_cell(near) {
+                Some(pos) => sim.add_food(sim.new_food(pos, FoodKind::Plain)),
+                None => break,
+            }
+        }
+        sim
+    }
+
+    /// Whether this is a versus match rather than a game for one player.
+    pub fn is_versus(&self) -> bool {
+        self.snakes.len() > 1
+    }
+
+    /// Picks a seed for a new session when none was given.
+    pub fn random_seed() -> u64 {
+        rand::thread_rng().gen()
+    }
+
+    /// The grid of the level being played.
+    pub fn grid(&self) -> &Grid {
+        &self.level.grid
+    }
+
+    /// What is on every cell of the board.
+    pub fn occupancy(&self) -> &Occupancy {
+        &self.occupancy
+    }
+
+    /// Marks the cells taken by walls, snakes and food again. Only needed after
+    /// changing `snakes` or `foods` by hand; the simulation keeps track of its
+    /// own changes.
+    pub fn refresh(&mut self) {
+        self.occupancy = Occupancy::new(&self.level, &self.snakes, &self.foods);
+    }
+
+    /// Puts a piece of food on the board.
+    fn add_food(&mut self, food: Food) {
+        self.occupancy.set_food(food.pos, true);
+        self.foods.push(food);
+    }
+
+    /// Takes the piece of food at `index` off the board.
+    fn remove_food(&mut self, index: usize) -> Food {
+        let food = self.foods.remove(index);
+        self.occupancy.set_food(food.pos, false);
+        food
+    }
+
+    /// Picks a random cell that no snake, wall or food occupies, or `None` if the board is full.
+    /// Cells within `CLUSTER_RADIUS` of `near` are preferred, then cells in the level's food
+    /// zone; only when all of them are taken may food spawn elsewhere.
+    ///
+    /// We pick the n-th free cell of the occupancy grid, so this takes the same time however
+    /// full the board is, instead of retrying random cells until a free one turns up.
+    fn random_free_cell(&mut self, near: Option<GridPosition>) -> Option<GridPosition> {
+        let occupancy = &self.occupancy;
+
+        if let Some(center) = near {
+            let cluster: Vec<GridPosition> = (-CLUSTER_RADIUS..=CLUSTER_RADIUS)
+                .flat_map(|dy| (-CLUSTER_RADIUS..=CLUSTER_RADIUS).map(move |dx| (dx, dy)))
+                .filter(|(dx, dy)| dx.abs() + dy.abs() <= CLUSTER_RADIUS)
+                .map(|(dx, dy)| GridPosition::new(center.x + dx, center.y + dy))
+                .filter(|pos| occupancy.is_free(*pos))
+                .collect();
+            if !cluster.is_empty() {
+                return Some(cluster[self.rng.gen_range(0, cluster.len())]);
+            }
+        }
+
+        let zone: Vec<GridPosition> = self
+            .level
+            .food_zone
+            .iter()
+            .cloned()
+            .filter(|pos| occupancy.is_free(*pos))
+            .collect();
+        if !zone.is_empty() {
+            return Some(zone[self.rng.gen_range(0, zone.len())]);
+        }
+
+        let free = occupancy.free();
+        if free == 0 {
+            return None;
+        }
+        let n = self.rng.gen_range(0, free);
+        self.occupancy.nth_free(n)
+    }
+
+    /// The snake controlled by the first player.
+    pub fn snake(&self) -> &Snake {
+        &self.snakes[0]
+    }
+
+    /// How long a tick should last. The game speeds up as the snakes eat, along
+    /// the speed curve of the rules unless the mode is endless, and while any
+    /// snake is under a speed or slow effect.
+    pub fn tick_interval(&self) -> Duration {
+        let eaten = if self.rules.mode.speeds_up() {
+            self.snakes.iter().map(|snake| snake.eaten).max().unwrap_or(0)
+        } else {
+            0
+        };
+        let scale: f32 = [Effect::Speed, Effect::Slow]
+            .iter()
+            .filter(|effect| self.snakes.iter().any(|snake| snake.effects.is_active(**effect)))
+            .map(|effect| effect.time_scale())
+            .product();
+        self.rules.speed.interval(eaten).mul_f32(scale)
+    }
+
+    /// Game time left before the game ends, if the mode limits it.
+    pub fn time_left(&self) -> Option<Duration> {
+        let limit = self.rules.mode.time_limit()?;
+        Some(limit.checked_sub(self.game_time).unwrap_or_default())
+    }
+
+    /// Game time left before the given snake starves, if the mode lets it.
+    pub fn starves_in(&self, snake: usize) -> Option<Duration> {
+        let limit = self.rules.mode.starvation()?;
+        Some(limit.checked_sub(self.snakes[snake].hunger).unwrap_or_default())
+    }
+
+    /// A new piece of food of the given kind. Plain food rots away in survival.
+    fn new_food(&self, pos: GridPosition, kind: FoodKind) -> Food {
+        let mut food = Food::with_kind(pos, kind);
+        if kind == FoodKind::Plain {
+            food.left = self.rules.mode.food_decay();
+        }
+        food
+    }
+
+    /// Puts a new piece of food on a random free cell, close to `near` if
+    /// given. Returns whether there was room for it.
+    fn spawn_food(&mut self, near: Option<GridPosition>) -> bool {
+        let pos = match self.random_free_cell(near) {
+            Some(pos) => pos,
+            None => return false,
+        };
+        let kind = if self.rules.power_ups {
+            FoodKind::random(&mut self.rng)
+        } else {
+            FoodKind::Plain
+        };
+        let food = self.new_food(pos, kind);
+        self.add_food(food);
+        true
+    }
+
+    /// Puts `size` pieces of food on the board, close together.
+    fn spawn_cluster(&mut self, size: usize) {
+        let first = self.random_free_cell(None);
+        for _ in 0..size {
+            if !self.spawn_food(first) {
+                break;
+            }
+        }
+    }
+
+    /// Replaces a piece of food that was just eaten, as the spawn policy says.
+    fn replace_eaten(&mut self) {
+        match self.rules.food {
+            SpawnPolicy::Fixed(_) => {
+                self.spawn_food(None);
+            }
+            SpawnPolicy::AfterEat(delay) => self.pending.push(self.game_time + delay),
+            SpawnPolicy::Timed { .. } => {}
+            SpawnPolicy::Clustered(size) => {
+                if self.foods.is_empty() {
+                    self.spawn_cluster(size);
+                }
+            }
+        }
+    }
+
+    /// Puts food on the board that is due by now: pieces eaten a while ago under
+    /// `SpawnPolicy::AfterEat`, the next piece under `SpawnPolicy::Timed`.
+    fn spawn_due(&mut self) {
+        let now = self.game_time;
+        let due = self.pending.iter().filter(|at| **at <= now).count();
+        self.pending.retain(|at| *at > now);
+        for _ in 0..due {
+            self.spawn_food(None);
+        }
+        if let SpawnPolicy::Timed { every, max } = self.rules.food {
+            if self.next_timed <= now {
+                self.next_timed += every;
+                if self.foods.len() < max {
+                    self.spawn_food(None);
+                }
+            }
+        }
+    }
+
+    /// With several snakes on the board, a head that runs into another snake is
+    /// lethal. When two heads meet, both snakes die.
+    fn check_collisions(&mut self) {
+        let crashed: Vec<bool> = self
+            .snakes
+            .iter()
+            .enumerate()
+            .map(|(i, snake)| {
+                self.snakes.iter().enumerate().filter(|(j, _)| *j != i).any(|(_, other)| {
+                    other.head.pos == snake.head.pos || other.body.iter().any(|seg| seg.pos == snake.head.pos)
+                })
+            })
+            .collect();
+        for (snake, crashed) in self.snakes.iter_mut().zip(crashed) {
+            if crashed && !snake.ate.is_some_and(Ate::is_lethal) {
+                snake.ate = Some(Ate::Snake);
+            }
+        }
+    }
+
+    /// The snake with the most points, or `None` if several share the lead.
+    fn leader(&self) -> Option<usize> {
+        let most = self.snakes.iter().map(|snake| snake.points).max();
+        let leaders: Vec<usize> = (0..self.snakes.len())
+            .filter(|&i| Some(self.snakes[i].points) == most)
+            .collect();
+        if leaders.len() == 1 {
+            Some(leaders[0])
+        } else {
+            None
+        }
+    }
+
+    /// Ends a round of a versus match and counts it on the scoreboard.
+    fn end_round(&mut self, winner: Option<usize>) {
+        self.winner = winner;
+        match winner {
+            Some(winner) => self.scoreboard.wins[winner] += 1,
+            None => self.scoreboard.draws += 1,
+        }
+    }
+
+    fn set_state(&mut self, to: GameStates) -> Event {
+        let from = self.game_states;
+        self.game_states = to;
+        Event::StateChanged { from, to }
+    }
+
+    /// Applies a single input right away without advancing the game. Returns the
+    /// resulting event, or `None` if the input had no effect.
+    pub fn apply(&mut self, input: Input) -> Option<Event> {
+        match input {
+            Input::Turn { snake, dir } => {
+                let accepted = self.snakes.get_mut(snake).is_some_and(|s| s.turn(dir));
+                if accepted {
+                    Some(Event::Turned { snake, dir })
+                } else {
+                    None
+                }
+            }
+            Input::Pause => match self.game_states {
+                GameStates::Pause => Some(self.set_state(GameStates::GameOn)),
+                GameStates::GameOn => Some(self.set_state(GameStates::Pause)),
+                _ => None,
+            },
+            Input::Restart => match self.game_states {
+                GameStates::GameOver | GameStates::Won => Some(self.set_state(GameStates::Restart)),
+                _ => None,
+            },
+        }
+    }
+
+    /// Applies `inputs` in order and then advances the game by one tick.
+    pub fn step(&mut self, inputs: &[Input]) -> Vec<Event> {
+        let mut events: Vec<Event> = inputs.iter().filter_map(|&i| self.apply(i)).collect();
+
+        match self.game_states {
+            GameStates::GameOver | GameStates::Pause | GameStates::Won => {}
+            GameStates::Restart => {
+                // Every game gets its own seed, drawn from the previous one, so that a single
+                // game can be reproduced from the seed shown for it.
+                let seed = self.rng.gen();
+                let level = self.level.clone();
+                let scoreboard = self.scoreboard.clone();
+                let max_ticks = self.max_ticks;
+                *self = Simulation::with_rules(level, seed, self.snakes.len(), self.rules);
+                self.scoreboard = scoreboard;
+                self.max_ticks = max_ticks;
+                events.push(Event::StateChanged {
+                    from: GameStates::Restart,
+                    to: GameStates::GameOn,
+                });
+            }
+            GameStates::GameOn => {
+                let interval = self.tick_interval();
+                self.game_time += interval;
+                self.tick += 1;
+                for snake in self.snakes.iter_mut() {
+                    snake.effects.advance(interval);
+                    snake.hunger += interval;
+                }
+                // Food that is not eaten in time makes way for plain food.
+                for food in self.foods.iter_mut() {
+                    food.left = food.left.map(|left| left.checked_sub(interval).unwrap_or_default());
+                }
+                let count = self.foods.len();
+                for food in self.foods.iter().filter(|food| food.left == Some(Duration::from_millis(0))) {
+                    self.occupancy.set_food(food.pos, false);
+                }
+                self.foods.retain(|food| food.left != Some(Duration::from_millis(0)));
+                for _ in self.foods.len()..count {
+                    if let Some(pos) = self.random_free_cell(None) {
+                        let food = self.new_food(pos, FoodKind::Plain);
+                        self.add_food(food);
+                    }
+                }
+                self.spawn_due();
+                // Here we do the actual updating of our game world. First we tell the snakes to update
+                // themselves, passing in a reference to our piece of food.
+                for snake in self.snakes.iter_mut() {
+                    let tail = snake.body.back().map(|seg| seg.pos);
+                    snake.update(&self.foods, &self.level);
+                    self.occupancy.enter(snake.head.pos);
+                    if let (None, Some(tail)) = (snake.ate, tail) {
+                        self.occupancy.leave(tail);
+                    }
+                }
+                if self.is_versus() {
+                    self.check_collisions();
+                }
+                let mut died = Vec::new();
+                let mut ate_food = false;
+                for i in 0..self.snakes.len() {
+                    // Next we check if the snake ate anything as it updated.
+                    if let Some(ate) = self.snakes[i].ate {
+                        events.push(Event::Ate { snake: i, ate });
+                        match ate {
+                            // If it ate a piece of food, we take it off the board and put new food
+                            // on it the way the spawn policy says.
+                            Ate::Food => {
+                                let head = self.snakes[i].head.pos;
+                                if let Some(index) = self.foods.iter().position(|food| food.pos == head) {
+                                    let food = self.remove_food(index);
+                                    self.snakes[i].eat(&food, &mut self.occupancy);
+                                    self.replace_eaten();
+                                }
+                                ate_food = true;
+                            }
+                            // If it ran into itself, a wall or another snake, it is dead.
+                            Ate::Itself | Ate::Wall | Ate::Snake => died.push(i),
+                        }
+                    } else if self.starves_in(i) == Some(Duration::from_millis(0)) {
+                        died.push(i);
+                    }
+                }
+                // Once the snakes fill the whole board or a lone snake reaches the goal of the
+                // level the game is won, and so is a time attack that everybody lives through.
+                let time_up = self.time_left() == Some(Duration::from_millis(0));
+                let goal_reached = !self.is_versus()
+                    && self.level.goal.is_some_and(|goal| goal.is_reached(self.snake()));
+                if (ate_food && (self.occupancy.is_full() || goal_reached)) || (time_up && died.is_empty()) {
+                    events.push(self.set_state(GameStates::Won));
+                }
+                for &i in &died {
+                    let snake = &mut self.snakes[i];
+                    let cause = snake.ate.and_then(Ate::cause).unwrap_or(Cause::Starved);
+                    snake.died = Some(cause);
+                    events.push(Event::Died { snake: i, cause });
+                }
+
+                let out_of_time = self.max_ticks.is_some_and(|max| self.tick >= max);
+                if self.game_states == GameStates::Won && self.is_versus() {
+                    // Nobody died, so whoever ate the most wins the round.
+                    let leader = self.leader();
+                    self.end_round(leader);
+                } else if out_of_time && died.is_empty() && self.game_states == GameStates::GameOn {
+                    // Time is up for everybody. In a versus match whoever ate the most wins the round.
+                    for (i, snake) in self.snakes.iter_mut().enumerate() {
+                        snake.died = Some(Cause::Timeout);
+                        events.push(Event::Died {
+                            snake: i,
+                            cause: Cause::Timeout,
+                        });
+                    }
+                    if self.is_versus() {
+                        let leader = self.leader();
+                        self.end_round(leader);
+                    }
+                    events.push(self.set_state(GameStates::GameOver));
+                } else if !died.is_empty() && self.game_states == GameStates::GameOn {
+                    // The game is over. In a versus match the snake that is left wins the round.
+                    if self.is_versus() {
+                        let survivors: Vec<usize> = (0..self.snakes.len()).filter(|i| !died.contains(i)).collect();
+                        self.end_round(if survivors.len() == 1 { Some(survivors[0]) } else { None });
+                    }
+                    events.push(self.set_state(GameStates::GameOver));
+                }
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_tick_moves_the_snake_one_cell() {
+        let mut sim = Simulation::new(Level::open(Grid::new(8, 6)), 1);
+        sim.foods.clear();
+        sim.refresh();
+        assert_eq!(sim.snake().head.pos, GridPosition::new(2, 3));
+        sim.step(&[Input::Turn {
+            snake: 0,
+            dir: Direction::Up,
+        }]);
+        assert_eq!(sim.snake().head.pos, GridPosition::new(2, 2));
+        assert_eq!(sim.snake().length(), 2);
+        assert_eq!(sim.tick, 1);
+        assert_eq!(sim.game_states, GameStates::GameOn);
+    }
+
+    /// Plays `ticks` ticks, turning the first snake every few of them, and
+    /// returns every event together with where the food was after each tick.
+    fn play(sim: &mut Simulation, ticks: u64) -> Vec<(Vec<Event>, Vec<GridPosition>)> {
+        let dirs = [Direction::Up, Direction::Left, Direction::Down, Direction::Right];
+        (0..ticks)
+            .map(|tick| {
+                let inputs = match tick % 7 {
+                    0 => vec![Input::Turn {
+                        snake: 0,
+                        dir: dirs[(tick / 7) as usize % dirs.len()],
+                    }],
+                    _ => Vec::new(),
+                };
+                let events = sim.step(&inputs);
+                (events, sim.foods.iter().map(|food| food.pos).collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_and_inputs_play_the_same_game() {
+        let rules = Rules {
+            power_ups: true,
+            food: SpawnPolicy::Fixed(3),
+            ..Rules::default()
+        };
+        let level = Level::open(Grid::new(16, 10));
+        let mut a = Simulation::with_rules(level.clone(), 42, 1, rules);
+        let mut b = Simulation::with_rules(level, 42, 1, rules);
+        assert_eq!(play(&mut a, 300), play(&mut b, 300));
+        assert_eq!(a.game_states, b.game_states);
+        assert_eq!(a.snake().points, b.snake().points);
+    }
+
+    #[test]
+    fn filling_the_board_wins() {
+        let level = Level::open(Grid::new(4, 4));
+        let mut sim = Simulation::new(level, 1);
+        // Everything but the last cell of the bottom row is snake, and the food
+        // is right in front of its head.
+        let snake = &mut sim.snakes[0];
+        snake.head = Segment::new(GridPosition::new(2, 3));
+        snake.dir = Direction::Right;
+        snake.last_update_dir = Direction::Right;
+        snake.body = (0..4)
+            .flat_map(|y| (0..4).map(move |x| GridPosition::new(x, y)))
+            .filter(|pos| pos.y < 3 || pos.x < 2)
+            .map(Segment::new)
+            .collect();
+        sim.foods = vec![Food::new(GridPosition::new(3, 3))];
+        sim.refresh();
+
+        let events = sim.step(&[]);
+        assert!(events.contains(&Event::StateChanged {
+            from: GameStates::GameOn,
+            to: GameStates::Won,
+        }));
+        assert_eq!(sim.snake().length(), 16);
+    }
+
+    #[test]
+    fn more_food_than_fits_fills_the_board() {
+        let rules = Rules {
+            food: SpawnPolicy::Fixed(1_000_000),
+            ..Rules::default()
+        };
+        let sim = Simulation::with_rules(Level::open(Grid::new(4, 4)), 1, 1, rules);
+        assert_eq!(sim.foods.len(), 14);
+    }
+
+    #[test]
+    fn points_stop_at_the_largest_score() {
+        let mut sim = Simulation::new(Level::open(Grid::new(8, 6)), 1);
+        let head = sim.snake().head.pos;
+        sim.snakes[0].points = i32::MAX - 1;
+        sim.foods = vec![Food::with_kind(GridPosition::new(head.x + 1, head.y), FoodKind::Bonus)];
+        sim.refresh();
+        sim.step(&[]);
+        assert_eq!(sim.snake().points, i32::MAX);
+    }
+
+    #[test]
+    fn running_off_a_solid_board_is_lethal() {
+        let level = Level::parse("boundary solid\nmap\n......\n.....>\n......\n......\n").unwrap();
+        let mut sim = Simulation::new(level, 1);
+        let events = sim.step(&[]);
+        assert!(events.contains(&Event::Died {
+            snake: 0,
+            cause: Cause::Wall,
+        }));
+        assert_eq!(sim.game_states, GameStates::GameOver);
+    }
+
+    #[test]
+    fn running_into_the_other_snake_loses_the_round() {
+        // The first snake heads right into the cell the second one just left.
+        let level = Level::parse("map\n........\n.>......\n...^....\n........\n........\n").unwrap();
+        let mut sim = Simulation::with_players(level, 1, 2);
+        sim.foods.clear();
+        sim.refresh();
+        sim.step(&[]);
+        let events = sim.step(&[]);
+        assert!(events.contains(&Event::Died {
+            snake: 0,
+            cause: Cause::Opponent,
+        }));
+        assert_eq!(sim.snakes[1].died, None);
+        assert_eq!(sim.winner, Some(1));
+        assert_eq!(sim.scoreboard.wins, vec![0, 1]);
+    }
+}
@@ -0,0 +1,153 @@
+//! Where food comes from: how many pieces are on the board and when new ones
+//! turn up.
+//!
+//! A policy is written as its name followed by its numbers, which is how it
+//! appears in settings, replays and on the command line of `snake-sim`:
+//!
+//! * `fixed <n>` - there are always `n` pieces on the board; every piece eaten
+//!   is replaced right away. `fixed 1` is the classic game.
+//! * `after-eat <ms>` - a single piece, replaced `ms` milliseconds of game time
+//!   after it was eaten.
+//! * `timed <ms> <max>` - a new piece every `ms` milliseconds, as long as there
+//!   are fewer than `max`. Eating does not make new food turn up.
+//! * `clustered <n>` - once the board is empty, `n` pieces turn up at once,
+//!   close together.
+//!
+//! No count may be larger than the number of cells on the biggest board.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::sim::Grid;
+
+use serde::{Deserialize, Serialize};
+
+/// The most pieces of food a policy can ask for: one on every cell of the
+/// biggest board.
+pub const MAX_PIECES: u64 = Grid::MAX_SIZE as u64 * Grid::MAX_SIZE as u64;
+
+/// How food is put on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SpawnPolicy {
+    Fixed(usize),
+    AfterEat(Duration),
+    Timed { every: Duration, max: usize },
+    Clustered(usize),
+}
+
+impl SpawnPolicy {
+    /// The policies offered in the options, with their names.
+    pub const PRESETS: [(&'static str, SpawnPolicy); 5] = [
+        ("Classic", SpawnPolicy::Fixed(1)),
+        ("Three at once", SpawnPolicy::Fixed(3)),
+        ("Feeding frenzy", SpawnPolicy::Timed {
+            every: Duration::from_millis(500),
+            max: 20,
+        }),
+        ("Sparse", SpawnPolicy::AfterEat(Duration::from_secs(3))),
+        ("Clusters", SpawnPolicy::Clustered(5)),
+    ];
+
+    /// The name of the preset this policy is, if it is one.
+    pub fn name(self) -> Option<&'static str> {
+        SpawnPolicy::PRESETS
+            .iter()
+            .find(|(_, policy)| *policy == self)
+            .map(|(name, _)| *name)
+    }
+
+    /// Number of pieces put on the board when a game starts.
+    pub fn initial(self) -> usize {
+        match self {
+            SpawnPolicy::Fixed(n) | SpawnPolicy::Clustered(n) => n,
+            SpawnPolicy::AfterEat(_) | SpawnPolicy::Timed { .. } => 1,
+        }
+    }
+}
+
+impl Default for SpawnPolicy {
+    fn default() -> Self {
+        SpawnPolicy::Fixed(1)
+    }
+}
+
+impl fmt::Display for SpawnPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpawnPolicy::Fixed(n) => write!(f, "fixed {}", n),
+            SpawnPolicy::AfterEat(delay) => write!(f, "after-eat {}", delay.as_millis()),
+            SpawnPolicy::Timed { every, max } => write!(f, "timed {} {}", every.as_millis(), max),
+            SpawnPolicy::Clustered(n) => write!(f, "clustered {}", n),
+        }
+    }
+}
+
+impl FromStr for SpawnPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let name = fields.next().unwrap_or("");
+        let mut number = || -> Result<u64, String> {
+            match fields.next().map(str::parse) {
+                Some(Ok(n)) if n > 0 => Ok(n),
+                _ => Err(format!("`{}` needs a positive number, found `{}`", name, s)),
+            }
+        };
+        let count = |n: u64| -> Result<usize, String> {
+            if n > MAX_PIECES {
+                return Err(format!("`{}` allows at most {} pieces, found {}", name, MAX_PIECES, n));
+            }
+            Ok(n as usize)
+        };
+        let policy = match name {
+            "fixed" => SpawnPolicy::Fixed(count(number()?)?),
+            "after-eat" => SpawnPolicy::AfterEat(Duration::from_millis(number()?)),
+            "timed" => SpawnPolicy::Timed {
+                every: Duration::from_millis(number()?),
+                max: count(number()?)?,
+            },
+            "clustered" => SpawnPolicy::Clustered(count(number()?)?),
+            other => return Err(format!("unknown spawn policy `{}`", other)),
+        };
+        match fields.next() {
+            Some(extra) => Err(format!("unexpected `{}` after `{}`", extra, name)),
+            None => Ok(policy),
+        }
+    }
+}
+
+impl TryFrom<String> for SpawnPolicy {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SpawnPolicy> for String {
+    fn from(policy: SpawnPolicy) -> String {
+        policy.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        for (_, policy) in SpawnPolicy::PRESETS.iter() {
+            assert_eq!(policy.to_string().parse(), Ok(*policy));
+        }
+        assert!("fixed 0".parse::<SpawnPolicy>().is_err());
+        assert!("timed 500".parse::<SpawnPolicy>().is_err());
+        assert!("fixed 1 2".parse::<SpawnPolicy>().is_err());
+        assert!("fixed 2000000000".parse::<SpawnPolicy>().is_err());
+        assert!("clustered 1000001".parse::<SpawnPolicy>().is_err());
+        assert_eq!("fixed 1000000".parse(), Ok(SpawnPolicy::Fixed(1_000_000)));
+    }
+}
@@ -0,0 +1,71 @@
+//! Files the game keeps between launches, like the settings, the high scores
+//! and the progress in the campaign. They are all TOML.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Things that can go wrong while loading or saving one of the files.
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Write(toml::ser::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StoreError::Io(e) => write!(f, "{}", e),
+            StoreError::Parse(e) => write!(f, "{}", e),
+            StoreError::Write(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e)
+    }
+}
+
+pub fn load<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, StoreError> {
+    let text = fs::read_to_string(path)?;
+    toml::from_str(&text).map_err(StoreError::Parse)
+}
+
+/// Loads the file at `path`, falling back to the default when there is none
+/// yet or it cannot be read. `what` names the contents in the warning printed
+/// for a file that cannot be read.
+pub fn load_or_default<T: DeserializeOwned + Default>(path: Option<&Path>, what: &str) -> T {
+    let path = match path {
+        Some(path) => path,
+        None => return T::default(),
+    };
+    match load(path) {
+        Ok(value) => value,
+        Err(StoreError::Io(ref e)) if e.kind() == io::ErrorKind::NotFound => T::default(),
+        Err(e) => {
+            eprintln!("Ignoring {} in {}: {}", what, path.display(), e);
+            T::default()
+        }
+    }
+}
+
+/// Writes `value` to `path`, creating its directory if needed.
+pub fn save<T: Serialize, P: AsRef<Path>>(path: P, value: &T) -> Result<(), StoreError> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = toml::to_string(value).map_err(StoreError::Write)?;
+    fs::write(path, text)?;
+    Ok(())
+}